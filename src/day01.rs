@@ -1,35 +1,148 @@
+use crate::error::{checked_sum, Error};
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
+use std::fmt;
 
-fn process_line_part_1(line: &str) -> i32 {
-    let first_digit = line
-        .chars()
-        .find(|&c| c.is_ascii_digit())
-        .unwrap()
-        .to_string();
-    let last_digit = line
-        .chars()
-        .rev()
-        .find(|&c| c.is_ascii_digit())
-        .unwrap()
-        .to_string();
+pub static ENGLISH_DIGITS: [(&str, u32); 9] = [
+    ("one", 1),
+    ("two", 2),
+    ("three", 3),
+    ("four", 4),
+    ("five", 5),
+    ("six", 6),
+    ("seven", 7),
+    ("eight", 8),
+    ("nine", 9),
+];
 
-    first_digit.parse::<i32>().unwrap() * 10 + last_digit.parse::<i32>().unwrap()
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Source {
+    Numeral,
+    Word,
 }
 
-fn number_replace(line: &str) -> String {
-    let mut line = line.to_string();
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Source::Numeral => "numeral",
+            Source::Word => "word",
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Digit {
+    // Byte offset of the numeral or the start of the word
+    pub position: usize,
+    pub length: usize,
+    pub value: u32,
+    pub source: Source,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Scanner {
+    words: Vec<(String, u32)>,
+}
+
+impl Scanner {
+    pub fn new(words: &[(&str, u32)]) -> Self {
+        Scanner {
+            words: words
+                .iter()
+                .map(|(word, value)| (word.to_string(), *value))
+                .collect(),
+        }
+    }
+
+    pub fn english() -> Self {
+        Self::new(&ENGLISH_DIGITS)
+    }
+
+    // Every digit in the line, checking each position separately so that overlapping words such
+    // as "eightwo" give both digits. Where several words start at the same position the longest
+    // is used.
+    pub fn scan(&self, line: &str) -> Vec<Digit> {
+        line.char_indices()
+            .filter_map(|(position, c)| {
+                if let Some(value) = c.to_digit(10) {
+                    return Some(Digit {
+                        position,
+                        length: 1,
+                        value,
+                        source: Source::Numeral,
+                    });
+                }
+
+                self.words
+                    .iter()
+                    .filter(|(word, _)| line[position..].starts_with(word.as_str()))
+                    .max_by_key(|(word, _)| word.len())
+                    .map(|(word, value)| Digit {
+                        position,
+                        length: word.len(),
+                        value: *value,
+                        source: Source::Word,
+                    })
+            })
+            .collect()
+    }
 
-    line = line.replace("one", "o1e");
-    line = line.replace("two", "t2o");
-    line = line.replace("three", "th3ee");
-    line = line.replace("four", "fo4r");
-    line = line.replace("five", "fi5e");
-    line = line.replace("six", "s6x");
-    line = line.replace("seven", "se7en");
-    line = line.replace("eight", "ei8ht");
-    line = line.replace("nine", "ni9e");
+    // The first digit written out followed by the last, so words worth more than 9 keep all of
+    // their digits, or None when the line has no digits.
+    pub fn calibration_value(&self, line: &str) -> Result<Option<u64>, Error> {
+        let digits = self.scan(line);
+        let (Some(first), Some(last)) = (digits.first(), digits.last()) else {
+            return Ok(None);
+        };
 
-    line
+        format!("{}{}", first.value, last.value)
+            .parse()
+            .map(Some)
+            .map_err(|_| Error::Overflow)
+    }
+
+    pub fn total(&self, lines: &[String]) -> Result<u64, Error> {
+        let values = lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                self.calibration_value(line)?
+                    .ok_or(Error::NoDigits { line: index + 1 })
+            })
+            .collect::<Result<Vec<u64>, Error>>()?;
+
+        checked_sum(values)
+    }
+
+    // Each line with the digits found in it, its calibration value and the running total.
+    pub fn report(&self, lines: &[String]) -> Result<String, Error> {
+        let mut report = String::new();
+        let mut total: u64 = 0;
+
+        for (index, line) in lines.iter().enumerate() {
+            report += &format!("line {}: {}\n", index + 1, line);
+
+            for digit in self.scan(line) {
+                report += &format!(
+                    "    {:>3} {:<8} {:<8} -> {}\n",
+                    digit.position,
+                    digit.source,
+                    &line[digit.position..digit.position + digit.length],
+                    digit.value
+                );
+            }
+
+            match self.calibration_value(line)? {
+                Some(value) => {
+                    total = total.checked_add(value).ok_or(Error::Overflow)?;
+                    report += &format!("    value {}, total {}\n", value, total);
+                }
+                None => report += &format!("    no digits, total {}\n", total),
+            }
+        }
+
+        Ok(report)
+    }
 }
 
 #[aoc_generator(day1)]
@@ -38,14 +151,136 @@ fn parse_input(input: &str) -> Vec<String> {
 }
 
 #[aoc(day1, part1)]
-fn part1(lines: &[String]) -> i32 {
-    lines.iter().map(|line| process_line_part_1(line)).sum()
+fn part1(lines: &[String]) -> Result<u64, Error> {
+    Scanner::default().total(lines)
 }
 
 #[aoc(day1, part2)]
-fn part2(lines: &[String]) -> i32 {
-    lines
-        .iter()
-        .map(|line| process_line_part_1(&number_replace(line)))
-        .sum()
+fn part2(lines: &[String]) -> Result<u64, Error> {
+    Scanner::english().total(lines)
+}
+
+pub struct Day01;
+
+impl Solution for Day01 {
+    type Input = Vec<String>;
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input))
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solution::Answer;
+
+    #[test]
+    fn test1() {
+        let input = "1abc2\npqr3stu8vwx\na1b2c3d4e5f\ntreb7uchet";
+
+        assert_eq!(part1(&parse_input(input)), Ok(142));
+        assert_eq!(
+            Day01::part1(&Day01::parse(input).unwrap()).unwrap(),
+            Answer(142)
+        );
+    }
+
+    #[test]
+    fn test2() {
+        let input = concat!(
+            "two1nine\n",
+            "eightwothree\n",
+            "abcone2threexyz\n",
+            "xtwone3four\n",
+            "4nineeightseven2\n",
+            "zoneight234\n",
+            "7pqrstsixteen",
+        );
+
+        assert_eq!(part2(&parse_input(input)), Ok(281));
+    }
+
+    #[test]
+    fn test3() {
+        let scanner = Scanner::english();
+
+        assert_eq!(
+            scanner.scan("eightwo"),
+            vec![
+                Digit {
+                    position: 0,
+                    length: 5,
+                    value: 8,
+                    source: Source::Word,
+                },
+                Digit {
+                    position: 4,
+                    length: 3,
+                    value: 2,
+                    source: Source::Word,
+                },
+            ]
+        );
+        assert_eq!(scanner.calibration_value("oneight"), Ok(Some(18)));
+        assert_eq!(scanner.calibration_value("abc"), Ok(None));
+        assert_eq!(Scanner::default().calibration_value("one"), Ok(None));
+    }
+
+    #[test]
+    fn test4() {
+        let scanner = Scanner::new(&[("six", 6), ("sixteen", 16), ("zero", 0), ("twenty", 20)]);
+
+        assert_eq!(scanner.calibration_value("sixteen"), Ok(Some(1616)));
+        assert_eq!(scanner.calibration_value("twenty"), Ok(Some(2020)));
+        assert_eq!(scanner.calibration_value("sixteen3"), Ok(Some(163)));
+        assert_eq!(scanner.calibration_value("zero3twenty"), Ok(Some(20)));
+        assert_eq!(scanner.calibration_value("six"), Ok(Some(66)));
+        assert_eq!(
+            Scanner::new(&[("most", u32::MAX)]).calibration_value("most"),
+            Err(Error::Overflow)
+        );
+    }
+
+    #[test]
+    fn test5() {
+        let lines = parse_input("two1nine\nabc\n7pqrstsixteen");
+
+        assert_eq!(
+            Scanner::english().report(&lines),
+            Ok(concat!(
+                "line 1: two1nine\n",
+                "      0 word     two      -> 2\n",
+                "      3 numeral  1        -> 1\n",
+                "      4 word     nine     -> 9\n",
+                "    value 29, total 29\n",
+                "line 2: abc\n",
+                "    no digits, total 29\n",
+                "line 3: 7pqrstsixteen\n",
+                "      0 numeral  7        -> 7\n",
+                "      6 word     six      -> 6\n",
+                "    value 76, total 105\n",
+            )
+            .to_string())
+        );
+    }
+
+    #[test]
+    fn test6() {
+        let lines = parse_input("1abc2\nabc\n3");
+
+        assert_eq!(part1(&lines), Err(Error::NoDigits { line: 2 }));
+        assert_eq!(
+            Day01::part1(&lines).unwrap_err().to_string(),
+            "line 2 has no digits"
+        );
+    }
 }
@@ -1,161 +1,279 @@
+use crate::error::{checked_product, checked_sum, parse_all, Error};
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
 use nom::bytes::complete::tag;
+use nom::character::complete::alpha1;
 use nom::IResult;
+use std::cmp::max;
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Debug)]
-enum Colour {
-    Red,
-    Green,
-    Blue,
+pub static STANDARD_BAG: [(&str, u32); 3] = [("red", 12), ("green", 13), ("blue", 14)];
+pub static STANDARD_COLOURS: [&str; 3] = ["red", "green", "blue"];
+
+// Number of cubes of each colour, with colours that are absent counting as zero
+pub type Cubes = HashMap<String, u32>;
+
+pub fn cubes(counts: &[(&str, u32)]) -> Cubes {
+    counts
+        .iter()
+        .map(|(colour, count)| (colour.to_string(), *count))
+        .collect()
+}
+
+pub fn count(cubes: &Cubes, colour: &str) -> u32 {
+    cubes.get(colour).copied().unwrap_or(0)
 }
 
 #[derive(Debug)]
-struct Pull {
-    red: u32,
-    green: u32,
-    blue: u32,
-}
-
-impl Pull {
-    fn new(colours: &[(Colour, u32)]) -> Pull {
-        let mut pull = Pull {
-            red: 0,
-            green: 0,
-            blue: 0,
-        };
-
-        for (colour, count) in colours {
-            match colour {
-                Colour::Red => pull.red += count,
-                Colour::Green => pull.green += count,
-                Colour::Blue => pull.blue += count,
+pub struct Game {
+    pub index: u32,
+    pub pulls: Vec<Cubes>,
+}
+
+impl Game {
+    pub fn possible_with(&self, bag: &Cubes) -> bool {
+        self.pulls.iter().all(|pull| {
+            pull.iter()
+                .all(|(colour, pulled)| *pulled <= count(bag, colour))
+        })
+    }
+
+    // The fewest cubes of each colour that make every pull possible.
+    pub fn minimum_bag(&self) -> Cubes {
+        let mut bag = Cubes::new();
+
+        for pull in &self.pulls {
+            for (colour, pulled) in pull {
+                let minimum = bag.entry(colour.clone()).or_default();
+                *minimum = max(*minimum, *pulled);
             }
         }
 
-        pull
+        bag
     }
-}
-
-#[derive(Debug)]
-struct Game {
-    index: u32,
-    pulls: Vec<Pull>,
-}
 
-fn parse_red_count(input: &str) -> IResult<&str, (Colour, u32)> {
-    let (input, _) = tag(" ")(input)?;
-    let (input, count) = nom::character::complete::u32(input)?;
-    let (input, _) = tag(" ")(input)?;
-    let (input, colour) = tag("red")(input)?;
+    pub fn power(&self, colours: &[&str]) -> Result<u64, Error> {
+        let bag = self.minimum_bag();
 
-    if colour == "red" {
-        return Ok((input, (Colour::Red, count)));
+        checked_product(colours.iter().map(|colour| count(&bag, colour) as u64))
     }
-    Err(nom::Err::Failure(nom::error::Error {
-        input,
-        code: nom::error::ErrorKind::Fail,
-    }))
 }
 
-fn parse_green_count(input: &str) -> IResult<&str, (Colour, u32)> {
-    let (input, _) = tag(" ")(input)?;
-    let (input, count) = nom::character::complete::u32(input)?;
-    let (input, _) = tag(" ")(input)?;
-    let (input, colour) = tag("green")(input)?;
+#[derive(Debug, PartialEq)]
+pub struct ColourStats {
+    pub max: u32,
+    pub mean_per_pull: f64,
+}
+
+// Largest single pull and the average pulled per pull for every colour seen in any game.
+pub fn colour_stats(games: &[Game]) -> BTreeMap<String, ColourStats> {
+    let pulls: Vec<&Cubes> = games.iter().flat_map(|game| &game.pulls).collect();
+    let mut totals: BTreeMap<String, (u32, u64)> = BTreeMap::new();
 
-    if colour == "green" {
-        return Ok((input, (Colour::Green, count)));
+    for pull in &pulls {
+        for (colour, pulled) in *pull {
+            let (largest, total) = totals.entry(colour.clone()).or_default();
+            *largest = max(*largest, *pulled);
+            *total += *pulled as u64;
+        }
     }
-    Err(nom::Err::Failure(nom::error::Error {
-        input,
-        code: nom::error::ErrorKind::Fail,
-    }))
+
+    totals
+        .into_iter()
+        .map(|(colour, (largest, total))| {
+            (
+                colour,
+                ColourStats {
+                    max: largest,
+                    mean_per_pull: total as f64 / pulls.len() as f64,
+                },
+            )
+        })
+        .collect()
 }
 
-fn parse_blue_count(input: &str) -> IResult<&str, (Colour, u32)> {
+fn parse_count(input: &str) -> IResult<&str, (&str, u32)> {
     let (input, _) = tag(" ")(input)?;
     let (input, count) = nom::character::complete::u32(input)?;
     let (input, _) = tag(" ")(input)?;
-    let (input, colour) = tag("blue")(input)?;
+    let (input, colour) = alpha1(input)?;
 
-    if colour == "blue" {
-        return Ok((input, (Colour::Blue, count)));
-    }
-    Err(nom::Err::Failure(nom::error::Error {
-        input,
-        code: nom::error::ErrorKind::Fail,
-    }))
+    Ok((input, (colour, count)))
 }
 
-fn parse_pull_colours(input: &str) -> IResult<&str, Vec<(Colour, u32)>> {
-    nom::multi::separated_list1(
-        tag(","),
-        nom::branch::alt((parse_red_count, parse_green_count, parse_blue_count)),
-    )(input)
+fn parse_pull(input: &str) -> IResult<&str, Vec<(&str, u32)>> {
+    nom::multi::separated_list1(tag(","), parse_count)(input)
 }
 
-fn parse_pull(input: &str) -> IResult<&str, Pull> {
-    let (input, colours) = parse_pull_colours(input)?;
-    Ok((input, Pull::new(&colours)))
-}
+type Line<'a> = (u32, Vec<Vec<(&'a str, u32)>>);
 
-fn parse_line(input: &str) -> IResult<&str, Game> {
+fn parse_line(input: &str) -> IResult<&str, Line<'_>> {
     let (input, index) =
         nom::sequence::delimited(tag("Game "), nom::character::complete::u32, tag(":"))(input)?;
     let (input, pulls) = nom::multi::separated_list1(tag(";"), parse_pull)(input)?;
 
-    Ok((input, Game { index, pulls }))
+    Ok((input, (index, pulls)))
 }
 
-fn input_parser(input: &str) -> IResult<&str, Vec<Game>> {
+fn input_parser(input: &str) -> IResult<&str, Vec<Line<'_>>> {
     nom::multi::separated_list1(nom::character::complete::newline, parse_line)(input)
 }
 
-fn possible_pull(pull: &Pull) -> bool {
-    const NUM_RED: u32 = 12;
-    const NUM_GREEN: u32 = 13;
-    const NUM_BLUE: u32 = 14;
+// A colour can be named more than once in a pull, in which case the counts add up.
+fn pull_cubes(counts: &[(&str, u32)]) -> Result<Cubes, Error> {
+    let mut pull = Cubes::new();
 
-    pull.red <= NUM_RED && pull.green <= NUM_GREEN && pull.blue <= NUM_BLUE
+    for (colour, count) in counts {
+        let total = pull.entry(colour.to_string()).or_default();
+        *total = total.checked_add(*count).ok_or(Error::Overflow)?;
+    }
+
+    Ok(pull)
 }
 
 #[aoc_generator(day2)]
-fn parse_input(input: &str) -> Vec<Game> {
-    let games = if let Ok((_, games)) = input_parser(input) {
-        games
-    } else {
-        todo!()
-    };
-
-    games
+fn parse_input(input: &str) -> Result<Vec<Game>, Error> {
+    parse_all(input_parser, input)?
+        .into_iter()
+        .map(|(index, pulls)| {
+            Ok(Game {
+                index,
+                pulls: pulls
+                    .iter()
+                    .map(|counts| pull_cubes(counts))
+                    .collect::<Result<Vec<Cubes>, Error>>()?,
+            })
+        })
+        .collect()
 }
 
 #[aoc(day2, part1)]
-fn part1(games: &[Game]) -> u32 {
-    games
-        .iter()
-        .filter(|game| game.pulls.iter().all(possible_pull))
-        .map(|game| game.index)
-        .sum()
+fn part1(games: &[Game]) -> Result<u64, Error> {
+    let bag = cubes(&STANDARD_BAG);
+
+    checked_sum(
+        games
+            .iter()
+            .filter(|game| game.possible_with(&bag))
+            .map(|game| game.index as u64),
+    )
 }
 
 #[aoc(day2, part2)]
-fn part2(games: &[Game]) -> u32 {
-    let mut sum = 0;
-
-    for game in games {
-        let mut red_min = 0;
-        let mut green_min = 0;
-        let mut blue_min = 0;
-
-        for pull in &game.pulls {
-            red_min = std::cmp::max(red_min, pull.red);
-            green_min = std::cmp::max(green_min, pull.green);
-            blue_min = std::cmp::max(blue_min, pull.blue);
-        }
+fn part2(games: &[Game]) -> Result<u64, Error> {
+    let powers = games
+        .iter()
+        .map(|game| game.power(&STANDARD_COLOURS))
+        .collect::<Result<Vec<u64>, Error>>()?;
+
+    checked_sum(powers)
+}
 
-        sum += red_min * green_min * blue_min;
+pub struct Day02;
+
+impl Solution for Day02 {
+    type Input = Vec<Game>;
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
     }
 
-    sum
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solution::{solve, Part};
+
+    static INPUT: &str = concat!(
+        "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green\n",
+        "Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue\n",
+        "Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red\n",
+        "Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red\n",
+        "Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green",
+    );
+
+    #[test]
+    fn test1() {
+        let games = parse_input(INPUT).unwrap();
+
+        assert_eq!(part1(&games), Ok(8));
+        assert_eq!(part2(&games), Ok(2286));
+    }
+
+    #[test]
+    fn test2() {
+        let games = parse_input(INPUT).unwrap();
+
+        assert_eq!(
+            games[0].minimum_bag(),
+            cubes(&[("red", 4), ("green", 2), ("blue", 6)])
+        );
+        assert!(games[0].possible_with(&cubes(&[("red", 4), ("green", 2), ("blue", 6)])));
+        assert!(!games[0].possible_with(&cubes(&[("red", 4), ("blue", 6)])));
+
+        let games = parse_input("Game 7: 2 teal, 1 red; 3 teal, 4 ochre").unwrap();
+        assert_eq!(
+            games[0].minimum_bag(),
+            cubes(&[("teal", 3), ("red", 1), ("ochre", 4)])
+        );
+        assert_eq!(games[0].power(&["teal", "ochre"]), Ok(12));
+        assert_eq!(games[0].power(&STANDARD_COLOURS), Ok(0));
+    }
+
+    #[test]
+    fn test3() {
+        let games =
+            parse_input("Game 1: 2 red, 1 blue; 4 red\nGame 2: 3 blue, 1 blue; 6 red").unwrap();
+        let stats = colour_stats(&games);
+
+        assert_eq!(
+            stats.into_iter().collect::<Vec<(String, ColourStats)>>(),
+            vec![
+                (
+                    "blue".to_string(),
+                    ColourStats {
+                        max: 4,
+                        mean_per_pull: 1.25
+                    }
+                ),
+                (
+                    "red".to_string(),
+                    ColourStats {
+                        max: 6,
+                        mean_per_pull: 3.0
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test4() {
+        assert_eq!(
+            parse_input("Game one: 2 red").unwrap_err().to_string(),
+            "cannot parse input: unexpected \"one: 2 red\""
+        );
+        assert_eq!(
+            parse_input("Game 1: 4294967295 red, 1 red").map(|_| ()),
+            Err(Error::Overflow)
+        );
+        assert_eq!(
+            parse_input("Game 1: 4294967294 red, 1 red").unwrap()[0].minimum_bag(),
+            cubes(&[("red", u32::MAX)])
+        );
+
+        let answers = solve::<Day02>("Game 1: red", &[Part::One, Part::Two]);
+        assert_eq!(answers.len(), 2);
+        assert!(answers.iter().all(|(_, answer)| answer
+            .as_ref()
+            .is_err_and(|error| error.to_string().starts_with("cannot parse input"))));
+    }
 }
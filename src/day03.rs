@@ -1,129 +1,275 @@
+use crate::error::{checked_product, checked_sum, Error};
+use crate::grid::{neighbours, parse_grid, Neighbourhood};
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
-use grid_2d::{Coord, Grid, Size};
+use grid_2d::{Coord, Grid};
 use std::collections::HashMap;
+use std::ops::Range;
 
-fn is_symbol(c: char) -> bool {
-    matches!(c, '@' | '#' | '$' | '%' | '&' | '*' | '-' | '+' | '=' | '/')
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum Symbols {
+    // Anything other than a digit or '.'
+    #[default]
+    Any,
+    Only(Vec<char>),
 }
 
-fn adjacent_symbol(grid: &Grid<char>, coord: &Coord) -> Option<Coord> {
-    let test = Coord::new(coord.x - 1, coord.y - 1);
-    if is_symbol(*grid.get(test).unwrap_or(&'.')) {
-        return Some(test);
+impl Symbols {
+    pub fn contains(&self, c: char) -> bool {
+        match self {
+            Symbols::Any => !c.is_ascii_digit() && c != '.',
+            Symbols::Only(symbols) => symbols.contains(&c),
+        }
     }
+}
 
-    let test = Coord::new(coord.x, coord.y - 1);
-    if is_symbol(*grid.get(test).unwrap_or(&'.')) {
-        return Some(test);
-    }
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Number {
+    pub value: u64,
+    pub row: i32,
+    pub columns: Range<i32>,
+    // Every symbol touching any digit, in reading order
+    pub symbols: Vec<Coord>,
+}
 
-    let test = Coord::new(coord.x + 1, coord.y - 1);
-    if is_symbol(*grid.get(test).unwrap_or(&'.')) {
-        return Some(test);
+impl Number {
+    pub fn is_part_number(&self) -> bool {
+        !self.symbols.is_empty()
     }
+}
 
-    let test = Coord::new(coord.x - 1, coord.y);
-    if is_symbol(*grid.get(test).unwrap_or(&'.')) {
-        return Some(test);
-    }
+#[derive(Debug)]
+pub struct Schematic {
+    grid: Grid<char>,
+    numbers: Vec<Number>,
+}
 
-    let test = Coord::new(coord.x + 1, coord.y);
-    if is_symbol(*grid.get(test).unwrap_or(&'.')) {
-        return Some(test);
+impl Schematic {
+    pub fn new(grid: Grid<char>) -> Result<Self, Error> {
+        Self::with_rules(grid, &Symbols::default(), &Neighbourhood::default())
     }
 
-    let test = Coord::new(coord.x - 1, coord.y + 1);
-    if is_symbol(*grid.get(test).unwrap_or(&'.')) {
-        return Some(test);
+    pub fn with_rules(
+        grid: Grid<char>,
+        symbols: &Symbols,
+        neighbourhood: &Neighbourhood,
+    ) -> Result<Self, Error> {
+        let mut numbers = vec![];
+
+        for (row, cells) in grid.rows().enumerate() {
+            let row = row as i32;
+            let mut column = 0;
+
+            while column < cells.len() {
+                if !cells[column].is_ascii_digit() {
+                    column += 1;
+                    continue;
+                }
+
+                let start = column;
+                while column < cells.len() && cells[column].is_ascii_digit() {
+                    column += 1;
+                }
+
+                let digits: String = cells[start..column].iter().collect();
+                let columns = start as i32..column as i32;
+
+                let mut adjacent_symbols: Vec<Coord> = columns
+                    .clone()
+                    .flat_map(|x| neighbours(&grid, Coord::new(x, row), neighbourhood))
+                    .filter(|(_, c)| symbols.contains(**c))
+                    .map(|(coord, _)| coord)
+                    .collect();
+                adjacent_symbols.sort_by_key(|coord| (coord.y, coord.x));
+                adjacent_symbols.dedup();
+
+                numbers.push(Number {
+                    value: digits.parse().map_err(|_| Error::Overflow)?,
+                    row,
+                    columns,
+                    symbols: adjacent_symbols,
+                });
+            }
+        }
+
+        Ok(Schematic { grid, numbers })
     }
 
-    let test = Coord::new(coord.x, coord.y + 1);
-    if is_symbol(*grid.get(test).unwrap_or(&'.')) {
-        return Some(test);
+    pub fn part_numbers(&self) -> impl Iterator<Item = &Number> {
+        self.numbers.iter().filter(|number| number.is_part_number())
     }
 
-    let test = Coord::new(coord.x + 1, coord.y + 1);
-    if is_symbol(*grid.get(test).unwrap_or(&'.')) {
-        return Some(test);
+    // Product of the numbers around each `symbol` that touches exactly `count` numbers, keyed by
+    // the symbol's position and in reading order.
+    pub fn ratios(&self, symbol: char, count: usize) -> Result<Vec<(Coord, u64)>, Error> {
+        let mut numbers_around: HashMap<Coord, Vec<u64>> = HashMap::new();
+
+        for number in self.part_numbers() {
+            for coord in &number.symbols {
+                if self.grid.get(*coord) == Some(&symbol) {
+                    numbers_around.entry(*coord).or_default().push(number.value);
+                }
+            }
+        }
+
+        let mut ratios = numbers_around
+            .into_iter()
+            .filter(|(_, numbers)| numbers.len() == count)
+            .map(|(coord, numbers)| Ok((coord, checked_product(numbers)?)))
+            .collect::<Result<Vec<(Coord, u64)>, Error>>()?;
+        ratios.sort_by_key(|(coord, _)| (coord.y, coord.x));
+
+        Ok(ratios)
     }
 
-    None
+    pub fn gears(&self) -> Result<Vec<(Coord, u64)>, Error> {
+        self.ratios('*', 2)
+    }
 }
 
 #[aoc_generator(day3)]
-fn parse_input(input: &str) -> grid_2d::Grid<char> {
-    const WIDTH: u32 = 140;
-    const HEIGHT: u32 = 140;
-
-    Grid::new_iterator(
-        Size::new(WIDTH, HEIGHT),
-        input.chars().filter(|c| *c != '\n'),
-    )
+fn parse_input(input: &str) -> Result<Schematic, Error> {
+    Schematic::new(parse_grid(input, Some)?)
 }
 
 #[aoc(day3, part1)]
-fn part1(grid: &Grid<char>) -> u32 {
-    let mut seen_symbol = false;
-    let mut number = String::new();
-    let mut sum = 0;
+fn part1(schematic: &Schematic) -> Result<u64, Error> {
+    checked_sum(schematic.part_numbers().map(|number| number.value))
+}
+
+#[aoc(day3, part2)]
+fn part2(schematic: &Schematic) -> Result<u64, Error> {
+    checked_sum(schematic.gears()?.into_iter().map(|(_, ratio)| ratio))
+}
 
-    for (coord, cell) in grid.enumerate() {
-        if cell.is_ascii_digit() {
-            number.push(*cell);
+pub struct Day03;
 
-            if adjacent_symbol(grid, &coord).is_some() {
-                seen_symbol = true;
-            }
-        } else {
-            if seen_symbol {
-                sum += number.parse::<u32>().unwrap();
-                seen_symbol = false;
-            }
+impl Solution for Day03 {
+    type Input = Schematic;
 
-            number.clear();
-        }
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
     }
 
-    sum
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
 }
 
-#[aoc(day3, part2)]
-fn part2(grid: &Grid<char>) -> u32 {
-    let mut seen_symbol = false;
-    let mut symbol_coord = Coord::new(0, 0);
-    let mut number = String::new();
-    let mut numbers_seen_with_symbols: Vec<(u32, Coord)> = vec![];
-
-    for (coord, cell) in grid.enumerate() {
-        if cell.is_ascii_digit() {
-            number.push(*cell);
-
-            if let Some(coord) = adjacent_symbol(grid, &coord) {
-                seen_symbol = true;
-                symbol_coord = coord;
-            }
-        } else {
-            if seen_symbol && *grid.get(symbol_coord).unwrap() == '*' {
-                numbers_seen_with_symbols.push((number.parse::<u32>().unwrap(), symbol_coord));
-                seen_symbol = false;
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            number.clear();
-        }
+    static INPUT: &str = concat!(
+        "467..114..\n",
+        "...*......\n",
+        "..35..633.\n",
+        "......#...\n",
+        "617*......\n",
+        ".....+.58.\n",
+        "..592.....\n",
+        "......755.\n",
+        "...$.*....\n",
+        ".664.598..\n",
+    );
+
+    #[test]
+    fn test1() {
+        let schematic = parse_input(INPUT).unwrap();
+
+        assert_eq!(part1(&schematic), Ok(4361));
+        assert_eq!(part2(&schematic), Ok(467835));
+        assert_eq!(
+            schematic.gears(),
+            Ok(vec![(Coord::new(3, 1), 16345), (Coord::new(5, 8), 451490)])
+        );
+        assert_eq!(schematic.ratios('*', 1), Ok(vec![(Coord::new(3, 4), 617)]));
+    }
+
+    #[test]
+    fn test2() {
+        let schematic = parse_input("..12\n3*..\n").unwrap();
+
+        assert_eq!(
+            schematic.numbers,
+            vec![
+                Number {
+                    value: 12,
+                    row: 0,
+                    columns: 2..4,
+                    symbols: vec![Coord::new(1, 1)],
+                },
+                Number {
+                    value: 3,
+                    row: 1,
+                    columns: 0..1,
+                    symbols: vec![Coord::new(1, 1)],
+                },
+            ]
+        );
+        assert_eq!(schematic.gears(), Ok(vec![(Coord::new(1, 1), 36)]));
+    }
+
+    #[test]
+    fn test3() {
+        let schematic = parse_input("1.2\n.*.\n3.4\n").unwrap();
+
+        assert_eq!(part1(&schematic), Ok(10));
+        assert_eq!(schematic.gears(), Ok(vec![]));
+        assert_eq!(schematic.ratios('*', 4), Ok(vec![(Coord::new(1, 1), 24)]));
     }
 
-    let mut numbers_around_coord_counts: HashMap<Coord, Vec<u32>> = HashMap::new();
+    #[test]
+    fn test4() {
+        let grid = |input| parse_input(input).unwrap().grid;
+
+        assert_eq!(part1(&parse_input("12!\n").unwrap()), Ok(12));
+        assert_eq!(
+            Schematic::with_rules(
+                grid("12!\n"),
+                &Symbols::Only(vec!['*']),
+                &Neighbourhood::Moore
+            )
+            .unwrap()
+            .part_numbers()
+            .count(),
+            0
+        );
 
-    for (number, coord) in numbers_seen_with_symbols {
-        numbers_around_coord_counts
-            .entry(coord)
-            .or_default()
-            .push(number);
+        let diagonal = "1..\n.*.\n..2\n";
+        assert_eq!(part1(&parse_input(diagonal).unwrap()), Ok(3));
+        assert_eq!(
+            Schematic::with_rules(grid(diagonal), &Symbols::Any, &Neighbourhood::VonNeumann)
+                .unwrap()
+                .part_numbers()
+                .count(),
+            0
+        );
+
+        let knight = Neighbourhood::Custom(vec![Coord::new(2, 1), Coord::new(-2, -1)]);
+        let schematic =
+            Schematic::with_rules(grid(".1.\n*..\n..3\n"), &Symbols::Any, &knight).unwrap();
+        assert_eq!(
+            schematic
+                .part_numbers()
+                .map(|number| number.value)
+                .collect::<Vec<u64>>(),
+            vec![3]
+        );
     }
 
-    numbers_around_coord_counts
-        .iter()
-        .filter(|(_, numbers)| numbers.len() == 2)
-        .map(|(_, numbers)| numbers[0] * numbers[1])
-        .sum()
+    #[test]
+    fn test5() {
+        let input = "18446744073709551615*\n18446744073709551616*\n";
+
+        assert_eq!(
+            parse_input(&input[..22]).map(|schematic| schematic.numbers[0].value),
+            Ok(u64::MAX)
+        );
+        assert_eq!(parse_input(input).map(|_| ()), Err(Error::Overflow));
+    }
 }
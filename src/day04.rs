@@ -1,3 +1,5 @@
+use crate::error::{checked_sum, parse_all, Error};
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
 use nom::bytes::complete::tag;
 use nom::character::complete::{newline, space1};
@@ -7,13 +9,13 @@ use nom::IResult;
 use std::collections::HashSet;
 
 #[derive(Debug)]
-struct Card {
-    winning_numbers: HashSet<u32>,
-    selected_numbers: HashSet<u32>,
+pub struct Card {
+    pub winning_numbers: HashSet<u32>,
+    pub selected_numbers: HashSet<u32>,
 }
 
 impl Card {
-    fn matched_numbers(&self) -> HashSet<u32> {
+    pub fn matched_numbers(&self) -> HashSet<u32> {
         self.winning_numbers
             .intersection(&self.selected_numbers)
             .copied()
@@ -45,36 +47,174 @@ fn parse_line(input: &str) -> IResult<&str, Card> {
     ))
 }
 
+// What to do with copies won past the last card in the table
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Overrun {
+    Clamp,
+    Reject,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct Cascade {
+    // Copies of each card held at the end, originals included
+    pub copies: Vec<u64>,
+    // For each card, the earlier cards that won copies of it and how many each gave
+    pub sources: Vec<Vec<(usize, u64)>>,
+}
+
+impl Cascade {
+    // Cards are numbered from 1, as in the puzzle.
+    pub fn explain(&self, card: usize) -> Result<String, Error> {
+        let index = card
+            .checked_sub(1)
+            .filter(|&index| index < self.copies.len())
+            .ok_or(Error::NoCard {
+                card,
+                count: self.copies.len(),
+            })?;
+        let copies = self.copies[index];
+        let mut explanation = format!(
+            "card {}: {} {} (1 original",
+            card,
+            copies,
+            if copies == 1 { "copy" } else { "copies" }
+        );
+
+        for (source, copies) in &self.sources[index] {
+            explanation += &format!(", {} from card {}", copies, source + 1);
+        }
+        explanation += ")";
+
+        Ok(explanation)
+    }
+}
+
+pub fn cascade(match_counts: &[usize], overrun: Overrun) -> Result<Cascade, Error> {
+    let mut copies: Vec<u64> = vec![1; match_counts.len()];
+    let mut sources: Vec<Vec<(usize, u64)>> = vec![vec![]; match_counts.len()];
+
+    for (index, &matches) in match_counts.iter().enumerate() {
+        let last = index + matches;
+        if last >= match_counts.len() && overrun == Overrun::Reject {
+            return Err(Error::PastEnd {
+                card: index + 1,
+                matches,
+            });
+        }
+
+        for won in index + 1..=last.min(match_counts.len() - 1) {
+            copies[won] = copies[won]
+                .checked_add(copies[index])
+                .ok_or(Error::Overflow)?;
+            sources[won].push((index, copies[index]));
+        }
+    }
+
+    Ok(Cascade { copies, sources })
+}
+
 #[aoc_generator(day4)]
-fn parse_input(input: &str) -> Vec<Card> {
-    let (_, cards) = separated_list1(newline, parse_line)(input).unwrap();
+fn parse_input(input: &str) -> Result<Vec<Card>, Error> {
+    let cards = parse_all(separated_list1(newline, parse_line), input)?;
 
-    cards
+    Ok(cards)
 }
 
 #[aoc(day4, part1)]
-fn part1(cards: &[Card]) -> u32 {
-    cards
+fn part1(cards: &[Card]) -> Result<u64, Error> {
+    let points = cards
         .iter()
-        .map(|card| 2u32.pow(card.matched_numbers().len().try_into().unwrap()) / 2)
-        .sum()
+        .map(|card| {
+            2u64.checked_pow(card.matched_numbers().len().try_into().unwrap())
+                .map(|value| value / 2)
+                .ok_or(Error::Overflow)
+        })
+        .collect::<Result<Vec<u64>, Error>>()?;
+
+    checked_sum(points)
 }
 
 #[aoc(day4, part2)]
-fn part2(cards: &[Card]) -> usize {
+fn part2(cards: &[Card]) -> Result<u64, Error> {
     let match_counts: Vec<usize> = cards
         .iter()
         .map(|card| card.matched_numbers().len())
         .collect();
-    let mut card_counts = vec![1; match_counts.len()];
 
-    for index in 0..match_counts.len() {
-        let count = match_counts[index];
+    checked_sum(cascade(&match_counts, Overrun::Clamp)?.copies)
+}
 
-        for i in 0..count {
-            card_counts[index + i + 1] += card_counts[index];
-        }
+pub struct Day04;
+
+impl Solution for Day04 {
+    type Input = Vec<Card>;
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static INPUT: &str = concat!(
+        "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53\n",
+        "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19\n",
+        "Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1\n",
+        "Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83\n",
+        "Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36\n",
+        "Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11",
+    );
+
+    #[test]
+    fn test1() {
+        let cards = parse_input(INPUT).unwrap();
+
+        assert_eq!(part1(&cards), Ok(13));
+        assert_eq!(part2(&cards), Ok(30));
     }
 
-    card_counts.iter().sum()
+    #[test]
+    fn test2() {
+        let cascade = cascade(&[4, 2, 2, 1, 0, 0], Overrun::Reject).unwrap();
+
+        assert_eq!(cascade.copies, vec![1, 2, 4, 8, 14, 1]);
+        assert_eq!(
+            cascade.explain(4),
+            Ok(
+                "card 4: 8 copies (1 original, 1 from card 1, 2 from card 2, 4 from card 3)"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            cascade.explain(6),
+            Ok("card 6: 1 copy (1 original)".to_string())
+        );
+        assert_eq!(cascade.explain(0), Err(Error::NoCard { card: 0, count: 6 }));
+        assert_eq!(cascade.explain(7), Err(Error::NoCard { card: 7, count: 6 }));
+    }
+
+    #[test]
+    fn test3() {
+        assert_eq!(
+            cascade(&[1, 2, 0], Overrun::Reject),
+            Err(Error::PastEnd {
+                card: 2,
+                matches: 2
+            })
+        );
+        assert_eq!(
+            cascade(&[1, 2, 0], Overrun::Clamp).map(|cascade| cascade.copies),
+            Ok(vec![1, 2, 3])
+        );
+    }
 }
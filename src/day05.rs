@@ -1,3 +1,5 @@
+use crate::error::{parse_all, Error};
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
 use nom::bytes::complete::tag;
 use nom::character::complete::newline;
@@ -8,24 +10,24 @@ use range_ext::intersect::{Intersect, IntersectionExt};
 use std::ops::Range;
 
 #[derive(Debug)]
-struct Mapping {
-    destination: u64,
-    source: Range<u64>,
+pub struct Mapping {
+    pub destination: u64,
+    pub source: Range<u64>,
 }
 
 #[derive(Debug)]
-struct Almanac {
-    seeds: Vec<Range<u64>>,
-    seed_to_soil: Vec<Mapping>,
-    soil_to_fertilizer: Vec<Mapping>,
-    fertilizer_to_water: Vec<Mapping>,
-    water_to_light: Vec<Mapping>,
-    light_to_temperature: Vec<Mapping>,
-    temperature_to_humidity: Vec<Mapping>,
-    humidity_to_location: Vec<Mapping>,
+pub struct Almanac {
+    pub seeds: Vec<Range<u64>>,
+    pub seed_to_soil: Vec<Mapping>,
+    pub soil_to_fertilizer: Vec<Mapping>,
+    pub fertilizer_to_water: Vec<Mapping>,
+    pub water_to_light: Vec<Mapping>,
+    pub light_to_temperature: Vec<Mapping>,
+    pub temperature_to_humidity: Vec<Mapping>,
+    pub humidity_to_location: Vec<Mapping>,
 }
 
-fn perform_mapping(inputs: &[Range<u64>], mappings: &[Mapping]) -> Vec<Range<u64>> {
+pub fn perform_mapping(inputs: &[Range<u64>], mappings: &[Mapping]) -> Vec<Range<u64>> {
     let mut outputs: Vec<Range<u64>> = vec![];
 
     for input in inputs {
@@ -38,7 +40,7 @@ fn perform_mapping(inputs: &[Range<u64>], mappings: &[Mapping]) -> Vec<Range<u64
                 source: m.source.start..m.source.end,
             })
             .collect();
-        intersecting_mappings.sort_by(|a, b| a.source.start.cmp(&b.source.start));
+        intersecting_mappings.sort_by_key(|a| a.source.start);
 
         for mapping in intersecting_mappings {
             let source = mapping.source;
@@ -88,7 +90,7 @@ fn perform_mapping(inputs: &[Range<u64>], mappings: &[Mapping]) -> Vec<Range<u64
     outputs
 }
 
-fn seeds_to_locations(almanac: &Almanac) -> Vec<Range<u64>> {
+pub fn seeds_to_locations(almanac: &Almanac) -> Vec<Range<u64>> {
     perform_mapping(
         &perform_mapping(
             &perform_mapping(
@@ -110,16 +112,11 @@ fn seeds_to_locations(almanac: &Almanac) -> Vec<Range<u64>> {
     )
 }
 
-fn parse_seeds1(input: &str) -> IResult<&str, Vec<Range<u64>>> {
-    let (input, seeds) = preceded(
+fn parse_seeds1(input: &str) -> IResult<&str, Vec<u64>> {
+    preceded(
         tag("seeds: "),
         separated_list1(tag(" "), nom::character::complete::u64),
-    )(input)?;
-
-    Ok((
-        input,
-        seeds.into_iter().map(|seed| seed..seed + 1).collect(),
-    ))
+    )(input)
 }
 
 fn parse_seeds2(input: &str) -> IResult<&str, Vec<(u64, u64)>> {
@@ -136,82 +133,101 @@ fn parse_seeds2(input: &str) -> IResult<&str, Vec<(u64, u64)>> {
     )(input)
 }
 
-fn parse_map_line(input: &str) -> IResult<&str, Mapping> {
-    let (input, (destination, source, length)) = tuple((
+// Destination start, source start and length, as written in the almanac
+type RawMapping = (u64, u64, u64);
+
+fn parse_map_line(input: &str) -> IResult<&str, RawMapping> {
+    tuple((
         nom::character::complete::u64,
         preceded(tag(" "), nom::character::complete::u64),
         preceded(tag(" "), nom::character::complete::u64),
-    ))(input)?;
-
-    Ok((
-        input,
-        Mapping {
-            destination,
-            source: source..source + length,
-        },
-    ))
+    ))(input)
+}
+
+// Both ends of every mapping have to fit, so mapping a range within one can't overflow.
+fn to_mappings(raw: Vec<RawMapping>) -> Result<Vec<Mapping>, Error> {
+    raw.into_iter()
+        .map(|(destination, source, length)| {
+            destination.checked_add(length).ok_or(Error::Overflow)?;
+
+            Ok(Mapping {
+                destination,
+                source: source..source.checked_add(length).ok_or(Error::Overflow)?,
+            })
+        })
+        .collect()
 }
 
-fn parse_seed_to_soil_map(input: &str) -> IResult<&str, Vec<Mapping>> {
+fn parse_seed_to_soil_map(input: &str) -> IResult<&str, Vec<RawMapping>> {
     let (input, _) = preceded(many1(newline), tag("seed-to-soil map:\n"))(input)?;
     let (input, mapping) = separated_list1(newline, parse_map_line)(input)?;
 
     Ok((input, mapping))
 }
 
-fn parse_soil_to_fertilizer_map(input: &str) -> IResult<&str, Vec<Mapping>> {
+fn parse_soil_to_fertilizer_map(input: &str) -> IResult<&str, Vec<RawMapping>> {
     let (input, _) = preceded(many1(newline), tag("soil-to-fertilizer map:\n"))(input)?;
     let (input, mapping) = separated_list1(newline, parse_map_line)(input)?;
 
     Ok((input, mapping))
 }
 
-fn parse_fertilizer_to_water_map(input: &str) -> IResult<&str, Vec<Mapping>> {
+fn parse_fertilizer_to_water_map(input: &str) -> IResult<&str, Vec<RawMapping>> {
     let (input, _) = preceded(many1(newline), tag("fertilizer-to-water map:\n"))(input)?;
     let (input, mapping) = separated_list1(newline, parse_map_line)(input)?;
 
     Ok((input, mapping))
 }
 
-fn parse_water_to_light_map(input: &str) -> IResult<&str, Vec<Mapping>> {
+fn parse_water_to_light_map(input: &str) -> IResult<&str, Vec<RawMapping>> {
     let (input, _) = preceded(many1(newline), tag("water-to-light map:\n"))(input)?;
     let (input, mapping) = separated_list1(newline, parse_map_line)(input)?;
 
     Ok((input, mapping))
 }
 
-fn parse_light_to_temperature_map(input: &str) -> IResult<&str, Vec<Mapping>> {
+fn parse_light_to_temperature_map(input: &str) -> IResult<&str, Vec<RawMapping>> {
     let (input, _) = preceded(many1(newline), tag("light-to-temperature map:\n"))(input)?;
     let (input, mapping) = separated_list1(newline, parse_map_line)(input)?;
 
     Ok((input, mapping))
 }
 
-fn parse_temperature_to_humidity_map(input: &str) -> IResult<&str, Vec<Mapping>> {
+fn parse_temperature_to_humidity_map(input: &str) -> IResult<&str, Vec<RawMapping>> {
     let (input, _) = preceded(many1(newline), tag("temperature-to-humidity map:\n"))(input)?;
     let (input, mapping) = separated_list1(newline, parse_map_line)(input)?;
 
     Ok((input, mapping))
 }
 
-fn parse_humidity_to_location_map(input: &str) -> IResult<&str, Vec<Mapping>> {
+fn parse_humidity_to_location_map(input: &str) -> IResult<&str, Vec<RawMapping>> {
     let (input, _) = preceded(many1(newline), tag("humidity-to-location map:\n"))(input)?;
     let (input, mapping) = separated_list1(newline, parse_map_line)(input)?;
 
     Ok((input, mapping))
 }
 
-fn parse_input1(input: &str) -> Almanac {
-    let (input, seeds) = parse_seeds1(input).unwrap();
-    let (input, seed_to_soil) = parse_seed_to_soil_map(input).unwrap();
-    let (input, soil_to_fertilizer) = parse_soil_to_fertilizer_map(input).unwrap();
-    let (input, fertilizer_to_water) = parse_fertilizer_to_water_map(input).unwrap();
-    let (input, water_to_light) = parse_water_to_light_map(input).unwrap();
-    let (input, light_to_temperature) = parse_light_to_temperature_map(input).unwrap();
-    let (input, temperature_to_humidity) = parse_temperature_to_humidity_map(input).unwrap();
-    let (_, humidity_to_location) = parse_humidity_to_location_map(input).unwrap();
-
-    Almanac {
+fn parse_input1(input: &str) -> Result<Almanac, Error> {
+    let (input, seeds) = parse_seeds1(input)?;
+    let seeds = seeds
+        .into_iter()
+        .map(|seed| Ok(seed..seed.checked_add(1).ok_or(Error::Overflow)?))
+        .collect::<Result<Vec<Range<u64>>, Error>>()?;
+    let (input, seed_to_soil) = parse_seed_to_soil_map(input)?;
+    let seed_to_soil = to_mappings(seed_to_soil)?;
+    let (input, soil_to_fertilizer) = parse_soil_to_fertilizer_map(input)?;
+    let soil_to_fertilizer = to_mappings(soil_to_fertilizer)?;
+    let (input, fertilizer_to_water) = parse_fertilizer_to_water_map(input)?;
+    let fertilizer_to_water = to_mappings(fertilizer_to_water)?;
+    let (input, water_to_light) = parse_water_to_light_map(input)?;
+    let water_to_light = to_mappings(water_to_light)?;
+    let (input, light_to_temperature) = parse_light_to_temperature_map(input)?;
+    let light_to_temperature = to_mappings(light_to_temperature)?;
+    let (input, temperature_to_humidity) = parse_temperature_to_humidity_map(input)?;
+    let temperature_to_humidity = to_mappings(temperature_to_humidity)?;
+    let humidity_to_location = to_mappings(parse_all(parse_humidity_to_location_map, input)?)?;
+
+    Ok(Almanac {
         seeds,
         seed_to_soil,
         soil_to_fertilizer,
@@ -220,25 +236,31 @@ fn parse_input1(input: &str) -> Almanac {
         light_to_temperature,
         temperature_to_humidity,
         humidity_to_location,
-    }
+    })
 }
 
-fn parse_input2(input: &str) -> Almanac {
-    let (input, seeds_ranges) = parse_seeds2(input).unwrap();
-    let (input, seed_to_soil) = parse_seed_to_soil_map(input).unwrap();
-    let (input, soil_to_fertilizer) = parse_soil_to_fertilizer_map(input).unwrap();
-    let (input, fertilizer_to_water) = parse_fertilizer_to_water_map(input).unwrap();
-    let (input, water_to_light) = parse_water_to_light_map(input).unwrap();
-    let (input, light_to_temperature) = parse_light_to_temperature_map(input).unwrap();
-    let (input, temperature_to_humidity) = parse_temperature_to_humidity_map(input).unwrap();
-    let (_, humidity_to_location) = parse_humidity_to_location_map(input).unwrap();
+fn parse_input2(input: &str) -> Result<Almanac, Error> {
+    let (input, seeds_ranges) = parse_seeds2(input)?;
+    let (input, seed_to_soil) = parse_seed_to_soil_map(input)?;
+    let seed_to_soil = to_mappings(seed_to_soil)?;
+    let (input, soil_to_fertilizer) = parse_soil_to_fertilizer_map(input)?;
+    let soil_to_fertilizer = to_mappings(soil_to_fertilizer)?;
+    let (input, fertilizer_to_water) = parse_fertilizer_to_water_map(input)?;
+    let fertilizer_to_water = to_mappings(fertilizer_to_water)?;
+    let (input, water_to_light) = parse_water_to_light_map(input)?;
+    let water_to_light = to_mappings(water_to_light)?;
+    let (input, light_to_temperature) = parse_light_to_temperature_map(input)?;
+    let light_to_temperature = to_mappings(light_to_temperature)?;
+    let (input, temperature_to_humidity) = parse_temperature_to_humidity_map(input)?;
+    let temperature_to_humidity = to_mappings(temperature_to_humidity)?;
+    let humidity_to_location = to_mappings(parse_all(parse_humidity_to_location_map, input)?)?;
 
     let seeds = seeds_ranges
         .into_iter()
-        .map(|(start, length)| start..start + length)
-        .collect();
+        .map(|(start, length)| Ok(start..start.checked_add(length).ok_or(Error::Overflow)?))
+        .collect::<Result<Vec<Range<u64>>, Error>>()?;
 
-    Almanac {
+    Ok(Almanac {
         seeds,
         seed_to_soil,
         soil_to_fertilizer,
@@ -247,17 +269,18 @@ fn parse_input2(input: &str) -> Almanac {
         light_to_temperature,
         temperature_to_humidity,
         humidity_to_location,
-    }
+    })
 }
 
+// The parts read the seeds line differently, as single seeds or as ranges
 #[aoc_generator(day5)]
-fn parse_input(input: &str) -> String {
-    input.to_string()
+fn parse_input(input: &str) -> Result<(Almanac, Almanac), Error> {
+    Ok((parse_input1(input)?, parse_input2(input)?))
 }
 
 #[aoc(day5, part1)]
-fn part1(input: &str) -> u64 {
-    seeds_to_locations(&parse_input1(input))
+fn part1((almanac, _): &(Almanac, Almanac)) -> u64 {
+    seeds_to_locations(almanac)
         .iter()
         .map(|range| range.start)
         .min()
@@ -265,10 +288,63 @@ fn part1(input: &str) -> u64 {
 }
 
 #[aoc(day5, part2)]
-fn part2(input: &str) -> u64 {
-    seeds_to_locations(&parse_input2(input))
+fn part2((_, almanac): &(Almanac, Almanac)) -> u64 {
+    seeds_to_locations(almanac)
         .iter()
         .map(|range| range.start)
         .min()
         .unwrap()
 }
+
+pub struct Day05;
+
+impl Solution for Day05 {
+    type Input = (Almanac, Almanac);
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input).into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static MAPS: &str = concat!(
+        "\n\n",
+        "seed-to-soil map:\n50 98 2\n\n",
+        "soil-to-fertilizer map:\n0 15 37\n\n",
+        "fertilizer-to-water map:\n49 53 8\n\n",
+        "water-to-light map:\n88 18 7\n\n",
+        "light-to-temperature map:\n45 77 23\n\n",
+        "temperature-to-humidity map:\n0 69 1\n\n",
+        "humidity-to-location map:\n60 56 37\n",
+    );
+
+    #[test]
+    fn test1() {
+        let almanac = |seeds: &str| parse_input(&(seeds.to_string() + MAPS)).map(|_| ());
+
+        assert_eq!(almanac("seeds: 79 14 55 13"), Ok(()));
+        assert_eq!(
+            almanac("seeds: 18446744073709551615 5"),
+            Err(Error::Overflow)
+        );
+        assert_eq!(
+            parse_input(
+                &("seeds: 1 1".to_string()
+                    + &MAPS.replace("60 56 37", "60 18446744073709551615 37"))
+            )
+            .map(|_| ()),
+            Err(Error::Overflow)
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use crate::error::{parse_all, Error};
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
 use nom::bytes::complete::tag;
 use nom::character::complete::space1;
@@ -5,76 +7,138 @@ use nom::multi::separated_list1;
 use nom::IResult;
 
 #[derive(Debug)]
-struct Race {
-    time: u32,
-    distance: u32,
+pub struct Race {
+    pub time: u64,
+    pub distance: u64,
 }
 
-fn parse_times(input: &str) -> IResult<&str, Vec<u32>> {
+fn parse_times(input: &str) -> IResult<&str, Vec<u64>> {
     let (input, _) = tag("Time:")(input)?;
     let (input, _) = space1(input)?;
 
-    separated_list1(space1, nom::character::complete::u32)(input)
+    separated_list1(space1, nom::character::complete::u64)(input)
 }
 
-fn parse_distances(input: &str) -> IResult<&str, Vec<u32>> {
+fn parse_distances(input: &str) -> IResult<&str, Vec<u64>> {
     let (input, _) = tag("\nDistance:")(input)?;
     let (input, _) = space1(input)?;
 
-    separated_list1(space1, nom::character::complete::u32)(input)
+    separated_list1(space1, nom::character::complete::u64)(input)
 }
 
 #[aoc_generator(day6)]
-fn parse_input(input: &str) -> Vec<Race> {
-    let (input, times) = parse_times(input).unwrap();
-    let (_, distances) = parse_distances(input).unwrap();
+fn parse_input(input: &str) -> Result<Vec<Race>, Error> {
+    let (input, times) = parse_times(input)?;
+    let distances = parse_all(parse_distances, input)?;
+
+    if times.len() != distances.len() {
+        return Err(Error::Parse(format!(
+            "{} times but {} distances",
+            times.len(),
+            distances.len()
+        )));
+    }
 
-    times
+    Ok(times
         .into_iter()
         .zip(distances)
         .map(|(time, distance)| Race { time, distance })
-        .collect()
+        .collect())
+}
+
+pub fn count_wins(time: u64, record_distance: u64) -> Result<u64, Error> {
+    let mut win_count = 0;
+
+    for push_time in 1..time {
+        let speed = push_time;
+        let move_time = time - push_time;
+        let distance = speed.checked_mul(move_time).ok_or(Error::Overflow)?;
+
+        if distance > record_distance {
+            win_count += 1;
+        }
+    }
+
+    Ok(win_count)
 }
 
 #[aoc(day6, part1)]
-fn part1(races: &[Race]) -> u32 {
-    let mut total = 1;
+fn part1(races: &[Race]) -> Result<u64, Error> {
+    let mut total: u64 = 1;
 
     for race in races {
-        let mut win_count = 0;
-
-        for push_time in 1..race.time {
-            let speed = push_time;
-            let move_time = race.time - push_time;
-            let distance = speed * move_time;
+        total = total
+            .checked_mul(count_wins(race.time, race.distance)?)
+            .ok_or(Error::Overflow)?;
+    }
 
-            if distance > race.distance {
-                win_count += 1;
-            }
-        }
+    Ok(total)
+}
 
-        total *= win_count;
+// Part 2 reads the sheet without the spaces, so the numbers run together into one race
+fn concatenate(numbers: impl IntoIterator<Item = u64>) -> Result<u64, Error> {
+    let mut concatenated: u64 = 0;
+
+    for number in numbers {
+        let shift = 10u64
+            .checked_pow(number.checked_ilog10().unwrap_or(0) + 1)
+            .ok_or(Error::Overflow)?;
+        concatenated = concatenated
+            .checked_mul(shift)
+            .and_then(|concatenated| concatenated.checked_add(number))
+            .ok_or(Error::Overflow)?;
     }
 
-    total
+    Ok(concatenated)
 }
 
 #[aoc(day6, part2)]
-fn part2(_races: &[Race]) -> u64 {
-    const TIME: u64 = 35937366;
-    const DISTANCE: u64 = 212206012011044;
+fn part2(races: &[Race]) -> Result<u64, Error> {
+    let time = concatenate(races.iter().map(|race| race.time))?;
+    let distance = concatenate(races.iter().map(|race| race.distance))?;
 
-    let mut win_count = 0;
+    count_wins(time, distance)
+}
 
-    for push_time in 1..TIME {
-        let speed = push_time;
-        let move_time = TIME - push_time;
-        let distance = speed * move_time;
+pub struct Day06;
 
-        if distance > DISTANCE {
-            win_count += 1;
-        }
+impl Solution for Day06 {
+    type Input = Vec<Race>;
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static INPUT: &str = "Time:      7  15   30\nDistance:  9  40  200\n";
+
+    #[test]
+    fn test1() {
+        let races = parse_input(INPUT).unwrap();
+
+        assert_eq!(part1(&races), Ok(288));
+        assert_eq!(part2(&races), Ok(71503));
     }
 
-    win_count
+    #[test]
+    fn test2() {
+        assert_eq!(concatenate([7, 15, 30]), Ok(71530));
+        assert_eq!(concatenate([0, 10]), Ok(10));
+        assert_eq!(concatenate([]), Ok(0));
+        assert_eq!(concatenate([1844674407, 3709551615]), Ok(u64::MAX));
+        assert_eq!(concatenate([1844674407, 3709551616]), Err(Error::Overflow));
+        assert_eq!(concatenate([u64::MAX, 1]), Err(Error::Overflow));
+    }
 }
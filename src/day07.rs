@@ -1,13 +1,15 @@
+use crate::error::{checked_sum, parse_all, Error};
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
 use nom::character::complete::{alphanumeric1, newline, space1};
+use nom::combinator::verify;
 use nom::multi::separated_list1;
 use nom::sequence::separated_pair;
 use nom::IResult;
 use std::cmp::Ordering;
-use std::collections::HashMap;
 
-#[derive(Debug, PartialOrd, Ord, PartialEq, Eq)]
-enum HandType {
+#[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub enum HandType {
     FiveOfAKind,
     FourOfAKind,
     FullHouse,
@@ -17,108 +19,211 @@ enum HandType {
     HighCard,
 }
 
+// Hands have no order of their own, since strength depends on the rule set. RankedHand is the
+// ordered type.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-struct Hand {
-    cards: [u32; 5],
+pub struct Hand {
+    pub cards: [char; 5],
 }
 
-impl Hand {
-    fn hand_type(&self) -> HandType {
-        let mut counts: HashMap<u32, u32> = HashMap::new();
+impl std::fmt::Display for Hand {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.cards.iter().try_for_each(|card| write!(f, "{}", card))
+    }
+}
 
-        for card in self.cards {
-            *counts.entry(card).or_insert(0) += 1;
-        }
+// A hand evaluated under a particular rule set. The sort key packs the hand type strength above
+// the rank of each card in order, so hands ranked under the same rule set can be sorted without
+// re-evaluating them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RankedHand {
+    pub key: u32,
+    pub hand: Hand,
+    pub hand_type: HandType,
+}
 
-        // Move joker count to highest non-joker card count
-        if let Some(joker_count) = counts.remove(&1) {
-            let (dest_key, _) = counts
-                .iter()
-                .max_by_key(|entry| entry.1)
-                .unwrap_or((&14, &0));
-            *counts.entry(*dest_key).or_insert(0) += joker_count;
-        }
+// Weakest first. Keys only tie for different hands ranked under different rule sets, and the
+// cards break those ties so the order agrees with equality.
+impl Ord for RankedHand {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key
+            .cmp(&other.key)
+            .then_with(|| self.hand.cards.cmp(&other.hand.cards))
+    }
+}
 
-        let mut counts: Vec<u32> = counts.values().cloned().collect();
-        counts.sort();
+impl PartialOrd for RankedHand {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-        if counts == [5] {
-            return HandType::FiveOfAKind;
-        }
-        if counts == [1, 4] {
-            return HandType::FourOfAKind;
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleSet {
+    // Rank of each ASCII card character, weakest first, or None if the card isn't in the deck
+    ranks: [Option<u8>; 128],
+    wildcards: Vec<char>,
+}
+
+impl RuleSet {
+    const MAX_CARDS: usize = 16;
+
+    // Cards in `order` from weakest to strongest, any of which can be made wild.
+    pub fn new(order: &str, wildcards: &str) -> Result<Self, Error> {
+        if order.chars().count() > Self::MAX_CARDS {
+            return Err(Error::RuleSet(format!(
+                "more than {} cards",
+                Self::MAX_CARDS
+            )));
         }
-        if counts == [2, 3] {
-            return HandType::FullHouse;
+
+        let mut seen = vec![];
+        for card in order.chars() {
+            if !card.is_ascii() {
+                return Err(Error::RuleSet(format!("card {:?} isn't ASCII", card)));
+            }
+            if seen.contains(&card) {
+                return Err(Error::RuleSet(format!("card {:?} appears twice", card)));
+            }
+            seen.push(card);
         }
-        if counts == [1, 1, 3] {
-            return HandType::ThreeOfAKind;
+
+        if let Some(card) = wildcards.chars().find(|card| !order.contains(*card)) {
+            return Err(Error::RuleSet(format!(
+                "wildcard {:?} isn't in the order",
+                card
+            )));
         }
-        if counts == [1, 2, 2] {
-            return HandType::TwoPair;
+
+        Ok(Self::build(order, wildcards))
+    }
+
+    fn build(order: &str, wildcards: &str) -> Self {
+        let mut ranks = [None; 128];
+        for (rank, card) in order.chars().enumerate() {
+            ranks[card as usize] = Some(rank as u8);
         }
-        if counts == [1, 1, 1, 2] {
-            return HandType::OnePair;
+
+        Self {
+            ranks,
+            wildcards: wildcards.chars().collect(),
         }
-        if counts == [1, 1, 1, 1, 1] {
-            return HandType::HighCard;
+    }
+
+    pub fn standard() -> Self {
+        Self::build("23456789TJQKA", "")
+    }
+
+    pub fn jokers_wild() -> Self {
+        Self::build("J23456789TQKA", "J")
+    }
+
+    pub fn card_rank(&self, card: char) -> Result<usize, Error> {
+        self.ranks
+            .get(card as usize)
+            .copied()
+            .flatten()
+            .map(|rank| rank as usize)
+            .ok_or(Error::UnknownCard(card))
+    }
+
+    pub fn hand_type(&self, hand: &Hand) -> Result<HandType, Error> {
+        let mut counts = [0u8; Self::MAX_CARDS];
+        let mut wildcard_count = 0;
+
+        for card in hand.cards {
+            if self.wildcards.contains(&card) {
+                wildcard_count += 1;
+            } else {
+                counts[self.card_rank(card)?] += 1;
+            }
         }
-        panic!();
+
+        let (largest, second_largest) = counts.iter().fold((0, 0), |(first, second), &count| {
+            if count > first {
+                (count, first)
+            } else {
+                (first, second.max(count))
+            }
+        });
+
+        // Wildcards always do best joining the largest group
+        Ok(match (largest + wildcard_count, second_largest) {
+            (5, _) => HandType::FiveOfAKind,
+            (4, _) => HandType::FourOfAKind,
+            (3, 2) => HandType::FullHouse,
+            (3, _) => HandType::ThreeOfAKind,
+            (2, 2) => HandType::TwoPair,
+            (2, _) => HandType::OnePair,
+            _ => HandType::HighCard,
+        })
     }
-}
 
-impl PartialOrd for Hand {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        let left_type = self.hand_type();
-        let right_type = other.hand_type();
+    // The hand with every wildcard replaced by the card that makes it strongest: the most common
+    // other card, preferring the higher rank on ties, or the strongest card if all are wild.
+    pub fn upgrade(&self, hand: &Hand) -> Hand {
+        let is_wild = |card: &char| self.wildcards.contains(card);
 
-        let type_cmp = left_type.cmp(&right_type);
+        let target = hand
+            .cards
+            .iter()
+            .filter(|card| !is_wild(card))
+            .max_by_key(|&&card| {
+                let count = hand.cards.iter().filter(|&&c| c == card).count();
+                (count, self.card_rank(card).ok())
+            })
+            .copied()
+            .or_else(|| {
+                (0..128u8)
+                    .map(char::from)
+                    .filter(|card| !is_wild(card) && self.ranks[*card as usize].is_some())
+                    .max_by_key(|&card| self.card_rank(card).ok())
+            });
 
-        if type_cmp == Ordering::Equal {
-            return Some(other.cards.cmp(&self.cards));
+        match target {
+            Some(target) => Hand {
+                cards: hand
+                    .cards
+                    .map(|card| if is_wild(&card) { target } else { card }),
+            },
+            None => *hand,
         }
+    }
+
+    pub fn rank(&self, hand: &Hand) -> Result<RankedHand, Error> {
+        let hand_type = self.hand_type(hand)?;
+        let strength = HandType::HighCard as u32 - hand_type as u32;
+        let key = hand.cards.iter().try_fold(strength, |key, &card| {
+            Ok::<u32, Error>((key << 4) | self.card_rank(card)? as u32)
+        })?;
 
-        Some(type_cmp)
+        Ok(RankedHand {
+            key,
+            hand: *hand,
+            hand_type,
+        })
     }
 }
 
 #[derive(Debug, Copy, Clone)]
-struct Game {
-    hand: Hand,
-    bid: u32,
+pub struct Game {
+    pub hand: Hand,
+    pub bid: u32,
 }
 
-fn card_number_from_char(c: char) -> u32 {
-    match c {
-        '2' => 2,
-        '3' => 3,
-        '4' => 4,
-        '5' => 5,
-        '6' => 6,
-        '7' => 7,
-        '8' => 8,
-        '9' => 9,
-        'T' => 10,
-        'J' => 11,
-        'Q' => 12,
-        'K' => 13,
-        'A' => 14,
-        _ => panic!(),
-    }
-}
-
-fn str_to_hand(input: &str) -> Hand {
-    let mut cards = [0; 5];
+// Missing cards are left as '\0', which no rule set deals.
+pub fn str_to_hand(input: &str) -> Hand {
+    let mut cards = ['\0'; 5];
 
-    for (index, c) in input.chars().enumerate() {
-        cards[index] = card_number_from_char(c);
+    for (card, c) in cards.iter_mut().zip(input.chars()) {
+        *card = c;
     }
 
     Hand { cards }
 }
 
 fn parse_hand(input: &str) -> IResult<&str, Hand> {
-    let (input, hand_str) = alphanumeric1(input)?;
+    let (input, hand_str) = verify(alphanumeric1, |hand: &str| hand.chars().count() == 5)(input)?;
 
     Ok((input, str_to_hand(hand_str)))
 }
@@ -131,41 +236,302 @@ fn parse_game(input: &str) -> IResult<&str, Game> {
 }
 
 #[aoc_generator(day7)]
-fn parse_input(input: &str) -> Vec<Game> {
-    let (_, hands) = separated_list1(newline, parse_game)(input).unwrap();
+fn parse_input(input: &str) -> Result<Vec<Game>, Error> {
+    let hands = parse_all(separated_list1(newline, parse_game), input)?;
 
-    hands
+    Ok(hands)
 }
 
-#[aoc(day7, part1)]
-fn part1(games: &[Game]) -> u32 {
-    let mut games = games.to_vec();
-    games.sort_by(|a, b| a.hand.partial_cmp(&b.hand).unwrap());
+pub fn rank_games(games: &[Game], rules: &RuleSet) -> Result<Vec<(RankedHand, u32)>, Error> {
+    let mut ranked_games = games
+        .iter()
+        .map(|game| Ok((rules.rank(&game.hand)?, game.bid)))
+        .collect::<Result<Vec<(RankedHand, u32)>, Error>>()?;
+    ranked_games.sort_by_key(|(hand, _)| hand.key);
 
-    games
+    Ok(ranked_games)
+}
+
+pub fn total_winnings(games: &[Game], rules: &RuleSet) -> Result<u64, Error> {
+    let winnings = rank_games(games, rules)?
         .iter()
-        .rev()
         .enumerate()
-        .fold(0, |acc, (index, game)| acc + (index as u32 + 1) * game.bid)
+        .map(|(index, (_, bid))| (index as u64 + 1).checked_mul(*bid as u64))
+        .collect::<Option<Vec<u64>>>()
+        .ok_or(Error::Overflow)?;
+
+    checked_sum(winnings)
+}
+
+// One line per hand from weakest to strongest: rank, hand, detected type, the hand after
+// wildcard substitution, bid and winnings, followed by the total.
+pub fn winnings_report(games: &[Game], rules: &RuleSet) -> Result<String, Error> {
+    let mut report = String::new();
+    let mut total: u64 = 0;
+
+    for (index, (ranked, bid)) in rank_games(games, rules)?.iter().enumerate() {
+        let rank = index as u64 + 1;
+        let winnings = rank.checked_mul(*bid as u64).ok_or(Error::Overflow)?;
+        total = total.checked_add(winnings).ok_or(Error::Overflow)?;
+
+        report += &format!(
+            "{:>5} {} {:<12} {} {:>5} {:>10}\n",
+            rank,
+            ranked.hand,
+            format!("{:?}", ranked.hand_type),
+            rules.upgrade(&ranked.hand),
+            bid,
+            winnings
+        );
+    }
+
+    report += &format!("total {:>41}\n", total);
+
+    Ok(report)
+}
+
+#[aoc(day7, part1)]
+fn part1(games: &[Game]) -> Result<u64, Error> {
+    total_winnings(games, &RuleSet::standard())
 }
 
 #[aoc(day7, part2)]
-fn part2(games: &[Game]) -> u32 {
-    let mut games = games.to_vec();
+fn part2(games: &[Game]) -> Result<u64, Error> {
+    total_winnings(games, &RuleSet::jokers_wild())
+}
+
+pub struct Day07;
+
+impl Solution for Day07 {
+    type Input = Vec<Game>;
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BinaryHeap;
+
+    static INPUT: &str = "32T3K 765\nT55J5 684\nKK677 28\nKTJJT 220\nQQQJA 483";
+
+    #[test]
+    fn test1() {
+        assert_eq!(part1(&parse_input(INPUT).unwrap()), Ok(6440));
+    }
+
+    #[test]
+    fn test2() {
+        assert_eq!(part2(&parse_input(INPUT).unwrap()), Ok(5905));
+    }
+
+    #[test]
+    fn test3() {
+        let rules = RuleSet::new("23456789TJQKA", "2").unwrap();
+
+        assert_eq!(
+            rules.hand_type(&str_to_hand("2345A")),
+            Ok(HandType::OnePair)
+        );
+        assert_eq!(
+            rules.hand_type(&str_to_hand("22222")),
+            Ok(HandType::FiveOfAKind)
+        );
+        assert_eq!(
+            rules.hand_type(&str_to_hand("2KK33")),
+            Ok(HandType::FullHouse)
+        );
+        assert!(
+            rules.rank(&str_to_hand("2KK33")).unwrap().key
+                < rules.rank(&str_to_hand("3KK33")).unwrap().key
+        );
+    }
+
+    fn all_hands() -> Vec<Hand> {
+        let cards: Vec<char> = "23456789TJQKA".chars().collect();
+
+        (0..cards.len().pow(5))
+            .map(|mut index| {
+                let mut hand = Hand { cards: ['\0'; 5] };
+                for card in hand.cards.iter_mut() {
+                    *card = cards[index % cards.len()];
+                    index /= cards.len();
+                }
+                hand
+            })
+            .collect()
+    }
+
+    // Expects items already sorted, so the exhaustive part only needs linear comparisons
+    fn check_total_order<T: Ord + std::fmt::Debug>(items: &[T]) {
+        for pair in items.windows(2) {
+            assert_eq!(pair[0].cmp(&pair[1]), Ordering::Less, "{:?}", pair);
+            assert_ne!(pair[0], pair[1]);
+        }
+
+        // Spot check reflexivity, antisymmetry and transitivity on pseudo-random triples
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % items.len()
+        };
 
-    for game in &mut games {
-        for card in &mut game.hand.cards {
-            if *card == 11 {
-                *card = 1;
+        for _ in 0..10_000 {
+            let (a, b, c) = (&items[next()], &items[next()], &items[next()]);
+
+            assert_eq!(a.cmp(a), Ordering::Equal);
+            assert_eq!(a.cmp(b), b.cmp(a).reverse());
+            assert_eq!(a.cmp(b) == Ordering::Equal, a == b);
+            if a <= b && b <= c {
+                assert!(a <= c);
             }
         }
     }
 
-    games.sort_by(|a, b| a.hand.partial_cmp(&b.hand).unwrap());
+    // Strength worked out independently of the packed key: the type, then each card in order.
+    fn strength(hand: &Hand, hand_type: HandType, order: &str) -> (u8, [usize; 5]) {
+        (
+            HandType::HighCard as u8 - hand_type as u8,
+            hand.cards.map(|card| order.find(card).unwrap()),
+        )
+    }
 
-    games
-        .iter()
-        .rev()
-        .enumerate()
-        .fold(0, |acc, (index, game)| acc + (index as u32 + 1) * game.bid)
+    #[test]
+    fn test4() {
+        let rules = RuleSet::standard();
+        let mut ranked: Vec<RankedHand> = all_hands()
+            .iter()
+            .map(|hand| rules.rank(hand).unwrap())
+            .collect();
+        ranked.sort();
+        check_total_order(&ranked);
+
+        for pair in ranked.windows(2) {
+            assert!(
+                strength(&pair[0].hand, pair[0].hand_type, "23456789TJQKA")
+                    < strength(&pair[1].hand, pair[1].hand_type, "23456789TJQKA"),
+                "{:?}",
+                pair
+            );
+        }
+
+        let mut heap: BinaryHeap<RankedHand> = ["TTTTT", "AAAAA", "AAAAK", "2345A"]
+            .iter()
+            .map(|hand| rules.rank(&str_to_hand(hand)).unwrap())
+            .collect();
+        let strongest: Vec<String> = std::iter::from_fn(|| heap.pop())
+            .map(|ranked| ranked.hand.to_string())
+            .collect();
+        assert_eq!(strongest, ["AAAAA", "TTTTT", "AAAAK", "2345A"]);
+    }
+
+    #[test]
+    fn test5() {
+        let rules = RuleSet::jokers_wild();
+
+        let mut ranked: Vec<RankedHand> = all_hands()
+            .iter()
+            .map(|hand| rules.rank(hand).unwrap())
+            .collect();
+        ranked.sort();
+
+        check_total_order(&ranked);
+
+        for pair in ranked.windows(2) {
+            assert!(
+                strength(&pair[0].hand, pair[0].hand_type, "J23456789TQKA")
+                    < strength(&pair[1].hand, pair[1].hand_type, "J23456789TQKA"),
+                "{:?}",
+                pair
+            );
+        }
+    }
+
+    #[test]
+    fn test6() {
+        let report =
+            winnings_report(&parse_input(INPUT).unwrap(), &RuleSet::jokers_wild()).unwrap();
+
+        assert_eq!(
+            report,
+            concat!(
+                "    1 32T3K OnePair      32T3K   765        765\n",
+                "    2 KK677 TwoPair      KK677    28         56\n",
+                "    3 T55J5 FourOfAKind  T5555   684       2052\n",
+                "    4 QQQJA FourOfAKind  QQQQA   483       1932\n",
+                "    5 KTJJT FourOfAKind  KTTTT   220       1100\n",
+                "total                                      5905\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test7() {
+        let rules = RuleSet::jokers_wild();
+
+        assert_eq!(rules.upgrade(&str_to_hand("JJJJJ")), str_to_hand("AAAAA"));
+        assert_eq!(rules.upgrade(&str_to_hand("2J3J4")), str_to_hand("24344"));
+        assert_eq!(
+            RuleSet::standard().upgrade(&str_to_hand("KTJJT")),
+            str_to_hand("KTJJT")
+        );
+    }
+
+    #[test]
+    fn test8() {
+        let rules = RuleSet::new("ZYX", "").unwrap();
+
+        assert!(
+            rules.rank(&str_to_hand("XXYZZ")).unwrap() < rules.rank(&str_to_hand("ZZZYX")).unwrap()
+        );
+        assert_eq!(
+            rules.rank(&str_to_hand("XXYZZ")).unwrap().hand_type,
+            HandType::TwoPair
+        );
+        assert_eq!(rules.rank(&str_to_hand("23")), Err(Error::UnknownCard('2')));
+        assert_eq!(
+            total_winnings(&parse_input("ZZZZZ 1").unwrap(), &RuleSet::standard()),
+            Err(Error::UnknownCard('Z'))
+        );
+        assert!(parse_input("2345 1").is_err());
+        assert!(parse_input("2345AK 1").is_err());
+    }
+
+    #[test]
+    fn test9() {
+        assert_eq!(
+            RuleSet::new("23456789TJQKA", "Z"),
+            Err(Error::RuleSet(
+                "wildcard 'Z' isn't in the order".to_string()
+            ))
+        );
+        assert_eq!(
+            RuleSet::new("23456789TJQKAé", ""),
+            Err(Error::RuleSet("card 'é' isn't ASCII".to_string()))
+        );
+        assert_eq!(
+            RuleSet::new("0123456789ABCDEFG", ""),
+            Err(Error::RuleSet("more than 16 cards".to_string()))
+        );
+        assert_eq!(
+            RuleSet::new("23456789TJQKA2", ""),
+            Err(Error::RuleSet("card '2' appears twice".to_string()))
+        );
+        assert_eq!(
+            RuleSet::new("J23456789TQKA", "J"),
+            Ok(RuleSet::jokers_wild())
+        );
+    }
 }
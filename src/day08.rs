@@ -1,21 +1,118 @@
+use crate::error::{parse_all, Error};
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
+use itertools::Itertools;
 use nom::bytes::complete::tag;
-use nom::character::complete::{alpha1, newline};
+use nom::character::complete::{alpha1, alphanumeric1, newline};
 use nom::multi::{many1, separated_list1};
 use nom::sequence::{delimited, preceded, separated_pair};
 use nom::IResult;
-use num::integer::lcm;
+use num::integer::Integer;
 use std::collections::HashMap;
 
-type Maps = HashMap<String, (String, String)>;
+pub type NodeId = u16;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Turn {
+    Left,
+    Right,
+}
+
+impl TryFrom<char> for Turn {
+    type Error = Error;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            'L' => Ok(Turn::Left),
+            'R' => Ok(Turn::Right),
+            _ => Err(Error::Parse(format!("unexpected turn {:?}", c))),
+        }
+    }
+}
+
+// Node names are interned into dense ids so traversal is just indexing into the adjacency table.
+#[derive(Debug, Clone, Default)]
+pub struct Maps {
+    names: Vec<String>,
+    ids: HashMap<String, NodeId>,
+    edges: Vec<[NodeId; 2]>,
+}
+
+impl Maps {
+    // New nodes lead back to themselves until their edges are filled in.
+    pub fn intern(&mut self, name: &str) -> Result<NodeId, Error> {
+        if let Some(&id) = self.ids.get(name) {
+            return Ok(id);
+        }
+
+        let id = NodeId::try_from(self.names.len())
+            .map_err(|_| Error::Parse(format!("more than {} nodes", NodeId::MAX as usize + 1)))?;
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.edges.push([id, id]);
+
+        Ok(id)
+    }
+
+    pub fn id(&self, name: &str) -> Option<NodeId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: NodeId) -> &str {
+        &self.names[id as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn next(&self, id: NodeId, turn: Turn) -> NodeId {
+        self.edges[id as usize][turn as usize]
+    }
+
+    pub fn nodes_matching(&self, predicate: impl Fn(&str) -> bool) -> Vec<NodeId> {
+        (0..self.len() as NodeId)
+            .filter(|&id| predicate(self.name(id)))
+            .collect()
+    }
+
+    // Graphviz description of the network with each edge labelled by the turn that follows it.
+    pub fn to_dot(&self) -> String {
+        let mut dot = "digraph network {\n".to_string();
+
+        for (id, edges) in self.edges.iter().enumerate() {
+            for (turn, next) in ["L", "R"].iter().zip(edges) {
+                dot += &format!(
+                    "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                    self.name(id as NodeId),
+                    self.name(*next),
+                    turn
+                );
+            }
+        }
+
+        dot += "}\n";
+
+        dot
+    }
+
+    // Evaluates a node predicate once per node so traversal can test it by id.
+    pub fn mark(&self, predicate: impl Fn(&str) -> bool) -> Vec<bool> {
+        self.names.iter().map(|name| predicate(name)).collect()
+    }
+}
 
 fn parse_map(input: &str) -> IResult<&str, (&str, (&str, &str))> {
     separated_pair(
-        alpha1,
+        alphanumeric1,
         tag(" = "),
         delimited(
             tag("("),
-            separated_pair(alpha1, tag(", "), alpha1),
+            separated_pair(alphanumeric1, tag(", "), alphanumeric1),
             tag(")"),
         ),
     )(input)
@@ -25,66 +122,430 @@ fn parse_turns(input: &str) -> IResult<&str, &str> {
     alpha1(input)
 }
 
-#[aoc_generator(day8)]
-fn parse_input(input: &str) -> (String, Maps) {
-    let (input, turns) = parse_turns(input).unwrap();
-    let turns = turns.to_string();
-    let (_, maps) = preceded(many1(newline), separated_list1(newline, parse_map))(input).unwrap();
-    let maps = maps
-        .into_iter()
-        .map(|(key, (left, right))| (key.to_string(), (left.to_string(), right.to_string())))
-        .collect();
+// The path a ghost takes through the (node, turn index) state space: a tail of steps leading into
+// a repeating cycle, with the steps at which the ghost stands on an end node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GhostCycle {
+    pub tail_length: u64,
+    pub cycle_length: u64,
+    // Steps before the cycle starts
+    pub tail_hits: Vec<u64>,
+    // Offsets from the start of the cycle
+    pub cycle_hits: Vec<u64>,
+}
+
+impl GhostCycle {
+    pub fn is_end_at(&self, step: u64) -> bool {
+        if step < self.tail_length {
+            self.tail_hits.contains(&step)
+        } else {
+            self.cycle_hits
+                .contains(&((step - self.tail_length) % self.cycle_length))
+        }
+    }
 
-    (turns, maps)
+    // The LCM of cycle lengths only gives the answer when the ghost's sole end node visit lands
+    // exactly at the end of each cycle.
+    pub fn is_simple(&self) -> bool {
+        self.tail_hits.is_empty()
+            && self.cycle_hits.len() == 1
+            && self.tail_length + self.cycle_hits[0] == self.cycle_length
+    }
 }
 
-#[aoc(day8, part1)]
-fn part1(input: &(String, Maps)) -> u32 {
-    let (turns, maps) = input;
+// Steps until the walk reaches an end node, or None once a (node, turn index) state repeats
+// without reaching one, as the walk would then loop forever.
+pub fn steps_to_end(turns: &[Turn], maps: &Maps, start: NodeId, ends: &[bool]) -> Option<u64> {
+    let mut seen = vec![false; maps.len() * turns.len()];
+    let mut position = start;
     let mut steps = 0;
-    let mut position = "AAA".to_string();
-    let mut turn_iter = turns.chars().cycle();
 
-    while position != "ZZZ" {
-        let turn = turn_iter.next().expect("cycled iterator should never end");
+    loop {
+        if ends[position as usize] {
+            return Some(steps);
+        }
 
-        position = match turn {
-            'L' => maps[&position].0.clone(),
-            'R' => maps[&position].1.clone(),
-            _ => panic!(),
-        };
+        let turn_index = steps as usize % turns.len();
+        let seen = &mut seen[position as usize * turns.len() + turn_index];
+        if *seen {
+            return None;
+        }
+        *seen = true;
 
+        position = maps.next(position, turns[turn_index]);
         steps += 1;
     }
+}
+
+pub fn find_cycle(turns: &[Turn], maps: &Maps, start: NodeId, ends: &[bool]) -> GhostCycle {
+    let state = |node: NodeId, turn_index: usize| node as usize * turns.len() + turn_index;
+    let mut seen: Vec<Option<u64>> = vec![None; maps.len() * turns.len()];
+    let mut hits = vec![];
+    let mut position = start;
+    let mut step = 0;
 
-    steps
+    let tail_length = loop {
+        let turn_index = step as usize % turns.len();
+        let seen = &mut seen[state(position, turn_index)];
+
+        if let Some(first_seen) = *seen {
+            break first_seen;
+        }
+        *seen = Some(step);
+
+        if ends[position as usize] {
+            hits.push(step);
+        }
+
+        position = maps.next(position, turns[turn_index]);
+        step += 1;
+    };
+
+    let (tail_hits, cycle_hits) = hits.into_iter().partition(|&hit| hit < tail_length);
+
+    GhostCycle {
+        tail_length,
+        cycle_length: step - tail_length,
+        tail_hits,
+        cycle_hits: cycle_hits
+            .into_iter()
+            .map(|hit: u64| hit - tail_length)
+            .collect(),
+    }
+}
+
+// Combines x = a1 (mod m1) and x = a2 (mod m2) for moduli that need not be coprime, returning
+// None when the congruences have no common solution.
+fn combine_congruences(
+    (a1, m1): (i128, i128),
+    (a2, m2): (i128, i128),
+) -> Result<Option<(i128, i128)>, Error> {
+    let gcd = m1.extended_gcd(&m2);
+
+    if (a2 - a1) % gcd.gcd != 0 {
+        return Ok(None);
+    }
+
+    let modulus = (m1 / gcd.gcd).checked_mul(m2).ok_or(Error::Overflow)?;
+    let k = ((a2 - a1) / gcd.gcd)
+        .checked_mul(gcd.x)
+        .ok_or(Error::Overflow)?
+        .rem_euclid(m2 / gcd.gcd);
+    let x = m1
+        .checked_mul(k)
+        .and_then(|x| x.checked_add(a1))
+        .ok_or(Error::Overflow)?;
+
+    Ok(Some((x.rem_euclid(modulus), modulus)))
+}
+
+fn checked_lcm(a: u64, b: u64) -> Result<u64, Error> {
+    (a / a.gcd(&b)).checked_mul(b).ok_or(Error::Overflow)
+}
+
+// Earliest step at which every ghost is on an end node, if there is one.
+pub fn first_common_end(cycles: &[GhostCycle]) -> Result<Option<u64>, Error> {
+    let Some(max_tail) = cycles.iter().map(|cycle| cycle.tail_length).max() else {
+        return Ok(None);
+    };
+
+    if let Some(step) = (0..max_tail).find(|&step| cycles.iter().all(|cycle| cycle.is_end_at(step)))
+    {
+        return Ok(Some(step));
+    }
+
+    let mut earliest: Option<u64> = None;
+
+    // Past every tail each ghost is periodic, so each combination of cycle hits is a system of
+    // congruences.
+    for congruences in cycles
+        .iter()
+        .map(|cycle| {
+            cycle.cycle_hits.iter().map(|offset| {
+                (
+                    ((cycle.tail_length + offset) % cycle.cycle_length) as i128,
+                    cycle.cycle_length as i128,
+                )
+            })
+        })
+        .multi_cartesian_product()
+    {
+        let mut combined = Some((0, 1));
+        for congruence in congruences {
+            combined = match combined {
+                Some(combined) => combine_congruences(combined, congruence)?,
+                None => break,
+            };
+        }
+
+        if let Some((residue, modulus)) = combined {
+            let max_tail = max_tail as i128;
+            let step = if residue < max_tail {
+                residue + (max_tail - residue + modulus - 1) / modulus * modulus
+            } else {
+                residue
+            };
+            let step = u64::try_from(step).map_err(|_| Error::Overflow)?;
+
+            earliest = Some(earliest.map_or(step, |earliest| earliest.min(step)));
+        }
+    }
+
+    Ok(earliest)
+}
+
+#[aoc_generator(day8)]
+fn parse_input(input: &str) -> Result<(Vec<Turn>, Maps), Error> {
+    let (input, turns) = parse_turns(input)?;
+    let turns = turns
+        .chars()
+        .map(Turn::try_from)
+        .collect::<Result<Vec<Turn>, Error>>()?;
+    let lines = parse_all(
+        preceded(many1(newline), separated_list1(newline, parse_map)),
+        input,
+    )?;
+
+    let mut maps = Maps::default();
+    let mut defined = vec![];
+    for (node, (left, right)) in lines {
+        let node = maps.intern(node)?;
+        let edges = [maps.intern(left)?, maps.intern(right)?];
+        maps.edges[node as usize] = edges;
+
+        defined.resize(maps.len(), false);
+        defined[node as usize] = true;
+    }
+
+    // Every node that is led to has to be defined, or it would silently lead back to itself
+    defined.resize(maps.len(), false);
+    if let Some(id) = defined.iter().position(|defined| !defined) {
+        return Err(Error::Parse(format!(
+            "node {:?} is never defined",
+            maps.name(id as NodeId)
+        )));
+    }
+
+    Ok((turns, maps))
+}
+
+#[aoc(day8, part1)]
+fn part1((turns, maps): &(Vec<Turn>, Maps)) -> Result<u64, Error> {
+    let start = maps
+        .id("AAA")
+        .ok_or_else(|| Error::NoSolution("no node \"AAA\"".to_string()))?;
+    let ends = maps.mark(|name| name == "ZZZ");
+
+    steps_to_end(turns, maps, start, &ends)
+        .ok_or_else(|| Error::NoSolution("\"ZZZ\" can't be reached from \"AAA\"".to_string()))
 }
 
 #[aoc(day8, part2)]
-fn part2(input: &(String, Maps)) -> u64 {
-    let (turns, maps) = input;
-    let mut positions: Vec<String> = maps
-        .keys()
-        .filter(|key| key.chars().nth(2).unwrap() == 'A')
-        .cloned()
+fn part2((turns, maps): &(Vec<Turn>, Maps)) -> Result<u64, Error> {
+    let ends = maps.mark(|name| name.ends_with('Z'));
+    let cycles: Vec<GhostCycle> = maps
+        .nodes_matching(|name| name.ends_with('A'))
+        .into_iter()
+        .map(|start| find_cycle(turns, maps, start, &ends))
         .collect();
-    let mut cycle_steps: Vec<u64> = vec![0; positions.len()];
 
-    for (position, steps) in positions.iter_mut().zip(cycle_steps.iter_mut()) {
-        let mut turn_iter = turns.chars().cycle();
+    if cycles.is_empty() {
+        return Err(Error::NoSolution("no nodes end in 'A'".to_string()));
+    }
+
+    if cycles.iter().all(GhostCycle::is_simple) {
+        cycles
+            .iter()
+            .map(|cycle| cycle.cycle_length)
+            .try_fold(1, checked_lcm)
+    } else {
+        first_common_end(&cycles)?.ok_or_else(|| {
+            Error::NoSolution("the ghosts never stand on end nodes together".to_string())
+        })
+    }
+}
 
-        while position.chars().nth(2).unwrap() != 'Z' {
-            let turn = turn_iter.next().expect("cycled iterator should never end");
+pub struct Day08;
 
-            *position = match turn {
-                'L' => maps[position].0.clone(),
-                'R' => maps[position].1.clone(),
-                _ => panic!(),
-            };
+impl Solution for Day08 {
+    type Input = (Vec<Turn>, Maps);
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static INPUT: &str = concat!(
+        "LR\n",
+        "\n",
+        "11A = (11B, XXX)\n",
+        "11B = (XXX, 11Z)\n",
+        "11Z = (11B, XXX)\n",
+        "22A = (22B, XXX)\n",
+        "22B = (22C, 22C)\n",
+        "22C = (22Z, 22Z)\n",
+        "22Z = (22B, 22B)\n",
+        "XXX = (XXX, XXX)",
+    );
 
-            *steps += 1;
+    #[test]
+    fn test1() {
+        let (turns, maps) = parse_input(INPUT).unwrap();
+        let ends = maps.mark(|name| name.ends_with('Z'));
+
+        assert_eq!(
+            find_cycle(&turns, &maps, maps.id("11A").unwrap(), &ends),
+            GhostCycle {
+                tail_length: 1,
+                cycle_length: 2,
+                tail_hits: vec![],
+                cycle_hits: vec![1],
+            }
+        );
+        assert_eq!(
+            find_cycle(&turns, &maps, maps.id("22A").unwrap(), &ends),
+            GhostCycle {
+                tail_length: 1,
+                cycle_length: 6,
+                tail_hits: vec![],
+                cycle_hits: vec![2, 5],
+            }
+        );
+    }
+
+    #[test]
+    fn test2() {
+        assert_eq!(part2(&parse_input(INPUT).unwrap()), Ok(6));
+    }
+
+    #[test]
+    fn test3() {
+        let cycles = [
+            GhostCycle {
+                tail_length: 3,
+                cycle_length: 4,
+                tail_hits: vec![1],
+                cycle_hits: vec![2],
+            },
+            GhostCycle {
+                tail_length: 0,
+                cycle_length: 6,
+                tail_hits: vec![],
+                cycle_hits: vec![1, 3],
+            },
+        ];
+
+        assert_eq!(first_common_end(&cycles), Ok(Some(1)));
+        assert_eq!(first_common_end(&cycles[..1]), Ok(Some(1)));
+        assert_eq!(
+            first_common_end(&[cycles[0].clone(), cycles[0].clone()]),
+            Ok(Some(1))
+        );
+
+        let cycles = [
+            GhostCycle {
+                tail_length: 3,
+                cycle_length: 4,
+                tail_hits: vec![],
+                cycle_hits: vec![2],
+            },
+            GhostCycle {
+                tail_length: 0,
+                cycle_length: 6,
+                tail_hits: vec![],
+                cycle_hits: vec![3],
+            },
+        ];
+
+        assert_eq!(first_common_end(&cycles), Ok(Some(9)));
+    }
+
+    #[test]
+    fn test4() {
+        let input = "LLR\n\nAAA = (BBB, BBB)\nBBB = (AAA, ZZZ)\nZZZ = (ZZZ, ZZZ)";
+
+        assert_eq!(part1(&parse_input(input).unwrap()), Ok(6));
+        assert_eq!(
+            part1(&parse_input("LR\n\nAAA = (AAA, AAA)\nZZZ = (ZZZ, ZZZ)").unwrap()),
+            Err(Error::NoSolution(
+                "\"ZZZ\" can't be reached from \"AAA\"".to_string()
+            ))
+        );
+        assert_eq!(
+            part1(&parse_input("L\n\nBBB = (ZZZ, ZZZ)\nZZZ = (ZZZ, ZZZ)").unwrap()),
+            Err(Error::NoSolution("no node \"AAA\"".to_string()))
+        );
+    }
+
+    #[test]
+    fn test5() {
+        let input = "LLR\n\nAAA = (BBB, BBB)\nBBB = (AAA, ZZZ)\nZZZ = (ZZZ, ZZZ)";
+        let (_, maps) = parse_input(input).unwrap();
+
+        assert_eq!(
+            maps.to_dot(),
+            concat!(
+                "digraph network {\n",
+                "    \"AAA\" -> \"BBB\" [label=\"L\"];\n",
+                "    \"AAA\" -> \"BBB\" [label=\"R\"];\n",
+                "    \"BBB\" -> \"AAA\" [label=\"L\"];\n",
+                "    \"BBB\" -> \"ZZZ\" [label=\"R\"];\n",
+                "    \"ZZZ\" -> \"ZZZ\" [label=\"L\"];\n",
+                "    \"ZZZ\" -> \"ZZZ\" [label=\"R\"];\n",
+                "}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test6() {
+        assert_eq!(
+            parse_input("L\n\nAAA = (BBB, ZZZ)\nZZZ = (ZZZ, ZZZ)").map(|_| ()),
+            Err(Error::Parse("node \"BBB\" is never defined".to_string()))
+        );
+
+        let mut maps = Maps::default();
+        for id in 0..=NodeId::MAX {
+            assert_eq!(maps.intern(&id.to_string()), Ok(id));
         }
+        assert_eq!(
+            maps.intern("overflow"),
+            Err(Error::Parse("more than 65536 nodes".to_string()))
+        );
     }
 
-    cycle_steps.into_iter().fold(1, lcm)
+    #[test]
+    fn test7() {
+        let no_end = "L\n\n11A = (11B, 11B)\n11B = (11A, 11A)\n22A = (22Z, 22Z)\n22Z = (22A, 22A)";
+        let out_of_step = "L\n\n11A = (11Z, 11Z)\n11Z = (11A, 11A)\n22A = (22B, 22B)\n22B = (22Z, 22Z)\n22Z = (22B, 22B)";
+
+        assert_eq!(
+            part2(&parse_input(no_end).unwrap()),
+            Err(Error::NoSolution(
+                "the ghosts never stand on end nodes together".to_string()
+            ))
+        );
+        assert_eq!(
+            part2(&parse_input(out_of_step).unwrap()),
+            Err(Error::NoSolution(
+                "the ghosts never stand on end nodes together".to_string()
+            ))
+        );
+        assert_eq!(
+            part2(&parse_input("L\n\nBBB = (ZZZ, ZZZ)\nZZZ = (ZZZ, ZZZ)").unwrap()),
+            Err(Error::NoSolution("no nodes end in 'A'".to_string()))
+        );
+    }
 }
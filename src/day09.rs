@@ -1,44 +1,264 @@
+use crate::error::{checked_sum, parse_all, Error};
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
 use nom::character::complete::{newline, space1};
 use nom::multi::separated_list1;
 use nom::IResult;
+use num::rational::Ratio;
+use num::traits::CheckedAdd;
 
-fn calculate_next(samples: &[i32]) -> i32 {
-    let differences: Vec<i32> = samples
-        .windows(2)
-        .map(|elements| elements[1] - elements[0])
-        .collect();
+// A sequence of samples together with its table of repeated differences, which ends in a row of
+// zeros. The leading entry of each row gives the Newton forward-difference form of the unique
+// polynomial through the samples, so values can be found at any index without extending the table.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Sequence {
+    differences: Vec<Vec<i128>>,
+}
+
+impl Sequence {
+    pub fn new(samples: &[i64]) -> Result<Self, Error> {
+        if samples.is_empty() {
+            return Err(Error::EmptySequence);
+        }
+
+        let mut differences: Vec<Vec<i128>> =
+            vec![samples.iter().map(|&sample| sample as i128).collect()];
+
+        // A single sample is taken to be constant
+        if samples.len() == 1 {
+            differences.push(vec![0]);
+        }
+
+        loop {
+            let last = differences.last().expect("table starts with the samples");
+
+            if last.iter().all(|&element| element == 0) {
+                break;
+            }
+            if last.len() == 1 {
+                return Err(Error::NoZeroDifferences);
+            }
+
+            let next = last
+                .windows(2)
+                .map(|elements| elements[1].checked_sub(elements[0]).ok_or(Error::Overflow))
+                .collect::<Result<Vec<i128>, Error>>()?;
+            differences.push(next);
+        }
 
-    if differences.iter().all(|element| *element == 0) {
-        return *samples.last().unwrap();
+        Ok(Self { differences })
     }
 
-    samples.last().unwrap() + calculate_next(&differences)
+    // Degree of the fitted polynomial, with the all-zero sequence given degree 0.
+    pub fn degree(&self) -> usize {
+        self.differences.len().saturating_sub(2)
+    }
+
+    // The rows up to the last one that isn't all zeros, which are all the polynomial depends on.
+    fn leading_rows(&self) -> &[Vec<i128>] {
+        &self.differences[..self.degree() + 1]
+    }
+
+    // Value at any integer index, where 0 is the first sample and negative indices extrapolate
+    // backwards. Uses f(x) = sum_k f_k * C(x, k) with the binomial generalised to negative x.
+    pub fn value_at(&self, index: i128) -> Result<i128, Error> {
+        let mut binomial: i128 = 1;
+        let mut value: i128 = 0;
+
+        for (k, row) in self.leading_rows().iter().enumerate() {
+            if k > 0 {
+                binomial = index
+                    .checked_sub(k as i128 - 1)
+                    .and_then(|factor| binomial.checked_mul(factor))
+                    .ok_or(Error::Overflow)?
+                    / k as i128;
+            }
+
+            value = row[0]
+                .checked_mul(binomial)
+                .and_then(|term| value.checked_add(term))
+                .ok_or(Error::Overflow)?;
+        }
+
+        Ok(value)
+    }
+
+    pub fn predict_forward(&self, steps: usize) -> Result<Vec<i128>, Error> {
+        let length = self.differences[0].len() as i128;
+
+        (0..steps as i128)
+            .map(|step| self.value_at(length + step))
+            .collect()
+    }
+
+    pub fn predict_backward(&self, steps: usize) -> Result<Vec<i128>, Error> {
+        (1..=steps as i128)
+            .map(|step| self.value_at(-step))
+            .collect()
+    }
+
+    // Coefficients of the fitted polynomial in x, from the constant term upwards.
+    pub fn polynomial(&self) -> Result<Vec<Ratio<i128>>, Error> {
+        let mut coefficients = vec![Ratio::from_integer(0); self.degree() + 1];
+        // Expansion of x(x - 1)...(x - k + 1)
+        let mut falling = vec![1i128];
+        let mut factorial = 1i128;
+
+        for (k, row) in self.leading_rows().iter().enumerate() {
+            if k > 0 {
+                let previous = k as i128 - 1;
+                let mut next = vec![0i128; falling.len() + 1];
+                for (power, term) in falling.iter().enumerate() {
+                    next[power + 1] = next[power + 1].checked_add(*term).ok_or(Error::Overflow)?;
+                    next[power] = term
+                        .checked_mul(previous)
+                        .and_then(|scaled| next[power].checked_sub(scaled))
+                        .ok_or(Error::Overflow)?;
+                }
+                falling = next;
+                factorial = factorial.checked_mul(k as i128).ok_or(Error::Overflow)?;
+            }
+
+            for (coefficient, term) in coefficients.iter_mut().zip(&falling) {
+                let term = row[0].checked_mul(*term).ok_or(Error::Overflow)?;
+                *coefficient = coefficient
+                    .checked_add(&Ratio::new(term, factorial))
+                    .ok_or(Error::Overflow)?;
+            }
+        }
+
+        Ok(coefficients)
+    }
 }
 
-fn parse_line(input: &str) -> IResult<&str, Vec<i32>> {
-    separated_list1(space1, nom::character::complete::i32)(input)
+fn parse_line(input: &str) -> IResult<&str, Vec<i64>> {
+    separated_list1(space1, nom::character::complete::i64)(input)
 }
 
 #[aoc_generator(day9)]
-fn parse_input(input: &str) -> Vec<Vec<i32>> {
-    let (_, lines) = separated_list1(newline, parse_line)(input).unwrap();
+fn parse_input(input: &str) -> Result<Vec<Vec<i64>>, Error> {
+    let lines = parse_all(separated_list1(newline, parse_line), input)?;
 
-    lines
+    Ok(lines)
 }
 
 #[aoc(day9, part1)]
-fn part1(sample_histories: &[Vec<i32>]) -> i32 {
-    sample_histories
+fn part1(sample_histories: &[Vec<i64>]) -> Result<i128, Error> {
+    let predictions = sample_histories
         .iter()
-        .map(|samples| calculate_next(samples))
-        .sum()
+        .map(|samples| Ok(Sequence::new(samples)?.predict_forward(1)?[0]))
+        .collect::<Result<Vec<i128>, Error>>()?;
+
+    checked_sum(predictions)
 }
 
 #[aoc(day9, part2)]
-fn part2(sample_histories: &[Vec<i32>]) -> i32 {
-    sample_histories
+fn part2(sample_histories: &[Vec<i64>]) -> Result<i128, Error> {
+    let predictions = sample_histories
         .iter()
-        .map(|samples| calculate_next(&samples.iter().rev().copied().collect::<Vec<i32>>()))
-        .sum()
+        .map(|samples| Ok(Sequence::new(samples)?.predict_backward(1)?[0]))
+        .collect::<Result<Vec<i128>, Error>>()?;
+
+    checked_sum(predictions)
+}
+
+pub struct Day09;
+
+impl Solution for Day09 {
+    type Input = Vec<Vec<i64>>;
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static INPUT: &str = "0 3 6 9 12 15\n1 3 6 10 15 21\n10 13 16 21 30 45";
+
+    #[test]
+    fn test1() {
+        assert_eq!(part1(&parse_input(INPUT).unwrap()), Ok(114));
+        assert_eq!(part2(&parse_input(INPUT).unwrap()), Ok(2));
+    }
+
+    #[test]
+    fn test2() {
+        let sequence = Sequence::new(&[1, 3, 6, 10, 15, 21]).unwrap();
+
+        assert_eq!(sequence.degree(), 2);
+        assert_eq!(sequence.predict_forward(3), Ok(vec![28, 36, 45]));
+        assert_eq!(sequence.predict_backward(3), Ok(vec![0, 0, 1]));
+        assert_eq!(
+            sequence.polynomial(),
+            Ok(vec![Ratio::new(1, 1), Ratio::new(3, 2), Ratio::new(1, 2)])
+        );
+    }
+
+    #[test]
+    fn test3() {
+        assert_eq!(Sequence::new(&[]), Err(Error::EmptySequence));
+        assert_eq!(Sequence::new(&[1, 2, 4, 8]), Err(Error::NoZeroDifferences));
+        assert_eq!(
+            Sequence::new(&[0, 0, 0]).unwrap().polynomial(),
+            Ok(vec![Ratio::new(0, 1)])
+        );
+        assert_eq!(
+            part1(&parse_input("1 2 4 8").unwrap()),
+            Err(Error::NoZeroDifferences)
+        );
+
+        // A single sample is constant
+        let single = Sequence::new(&[5]).unwrap();
+        assert_eq!(single.degree(), 0);
+        assert_eq!(single.predict_forward(2), Ok(vec![5, 5]));
+        assert_eq!(single.predict_backward(1), Ok(vec![5]));
+        assert_eq!(single.polynomial(), Ok(vec![Ratio::new(5, 1)]));
+        assert_eq!(part1(&parse_input("5").unwrap()), Ok(5));
+        assert_eq!(part2(&parse_input("5").unwrap()), Ok(5));
+    }
+
+    #[test]
+    fn test4() {
+        let step = i64::MAX / 2;
+        let sequence = Sequence::new(&[0, step, 2 * step]).unwrap();
+
+        assert_eq!(sequence.value_at(3), Ok(3 * step as i128));
+        assert_eq!(
+            sequence.value_at(i64::MAX as i128 * 8),
+            Err(Error::Overflow)
+        );
+    }
+
+    #[test]
+    fn test5() {
+        let alternating: Vec<i64> = (0..70)
+            .map(|index| if index % 2 == 0 { i64::MAX } else { i64::MIN })
+            .collect();
+        assert_eq!(Sequence::new(&alternating), Err(Error::Overflow));
+
+        let mut samples = vec![0; 34];
+        samples.extend([1, 35]);
+        let sequence = Sequence::new(&samples).unwrap();
+        assert_eq!(sequence.degree(), 34);
+        assert_eq!(sequence.value_at(0), Ok(0));
+        assert_eq!(sequence.value_at(34), Ok(1));
+        assert_eq!(sequence.polynomial(), Err(Error::Overflow));
+
+        assert_eq!(Sequence::new(&[5, 5, 5]).unwrap().value_at(1 << 70), Ok(5));
+        assert_eq!(
+            Sequence::new(&[1, 2, 3]).unwrap().value_at(1 << 70),
+            Ok((1 << 70) + 1)
+        );
+    }
 }
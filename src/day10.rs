@@ -1,5 +1,8 @@
+use crate::error::Error;
+use crate::grid::parse_grid;
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
-use grid_2d::{Coord, Grid, Size};
+use grid_2d::{Coord, Grid};
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq)]
 enum Direction {
@@ -10,7 +13,7 @@ enum Direction {
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
-enum Tile {
+pub enum Tile {
     NorthSouth, // |
     EastWest,   // -
     NorthEast,  // L
@@ -21,17 +24,17 @@ enum Tile {
     Start,      // S
 }
 
-fn char_to_tile(c: char) -> Tile {
+pub fn char_to_tile(c: char) -> Option<Tile> {
     match c {
-        '|' => Tile::NorthSouth,
-        '-' => Tile::EastWest,
-        'L' => Tile::NorthEast,
-        'J' => Tile::NorthWest,
-        '7' => Tile::SouthWest,
-        'F' => Tile::SouthEast,
-        '.' => Tile::Ground,
-        'S' => Tile::Start,
-        _ => panic!(),
+        '|' => Some(Tile::NorthSouth),
+        '-' => Some(Tile::EastWest),
+        'L' => Some(Tile::NorthEast),
+        'J' => Some(Tile::NorthWest),
+        '7' => Some(Tile::SouthWest),
+        'F' => Some(Tile::SouthEast),
+        '.' => Some(Tile::Ground),
+        'S' => Some(Tile::Start),
+        _ => None,
     }
 }
 
@@ -44,6 +47,15 @@ fn adjacent_tile(location: Coord, direction: Direction) -> Coord {
     }
 }
 
+fn opposite(direction: Direction) -> Direction {
+    match direction {
+        Direction::North => Direction::South,
+        Direction::East => Direction::West,
+        Direction::South => Direction::North,
+        Direction::West => Direction::East,
+    }
+}
+
 fn connected_directions(tile: Tile) -> [Direction; 2] {
     match tile {
         Tile::NorthSouth => [Direction::North, Direction::South],
@@ -63,10 +75,33 @@ fn connected_tiles(grid: &Grid<(Tile, bool)>, tile: Coord) -> Vec<Coord> {
         .collect()
 }
 
-fn find_loop(grid: &mut Grid<(Tile, bool)>, start: Coord) -> u32 {
+// The pipe hidden under the start tile, found from the two neighbours that connect back to it.
+fn start_tile(grid: &Grid<(Tile, bool)>, start: Coord) -> Result<Tile, Error> {
+    let connects = |direction: Direction| {
+        grid.get(adjacent_tile(start, direction))
+            .is_some_and(|(tile, _)| match tile {
+                Tile::Ground | Tile::Start => false,
+                _ => connected_directions(*tile).contains(&opposite(direction)),
+            })
+    };
+
+    [
+        Tile::NorthSouth,
+        Tile::EastWest,
+        Tile::NorthEast,
+        Tile::NorthWest,
+        Tile::SouthWest,
+        Tile::SouthEast,
+    ]
+    .into_iter()
+    .find(|tile| connected_directions(*tile).into_iter().all(connects))
+    .ok_or_else(|| Error::Parse("start doesn't connect to two pipes".to_string()))
+}
+
+pub fn find_loop(grid: &mut Grid<(Tile, bool)>, start: Coord) -> u64 {
     let mut loop_length = 0;
     let mut current_position = start;
-    let mut previous_position = adjacent_tile(start, Direction::South);
+    let mut previous_position = connected_tiles(grid, start)[0];
 
     loop {
         grid.get_mut(current_position).unwrap().1 = true;
@@ -92,36 +127,33 @@ fn find_loop(grid: &mut Grid<(Tile, bool)>, start: Coord) -> u32 {
 }
 
 #[aoc_generator(day10)]
-fn parse_input(input: &str) -> (Grid<(Tile, bool)>, Coord) {
-    const WIDTH: u32 = 140;
-    const HEIGHT: u32 = 140;
-
-    let mut grid = Grid::new_iterator(
-        Size::new(WIDTH, HEIGHT),
-        input
-            .chars()
-            .filter(|c| *c != '\n')
-            .map(|c| (char_to_tile(c), false)),
-    );
-
-    let (start, _) = grid
-        .enumerate()
-        .find(|(_, (tile, _))| *tile == Tile::Start)
-        .unwrap();
-    grid.get_mut(start).unwrap().0 = Tile::SouthWest;
+fn parse_input(input: &str) -> Result<(Grid<(Tile, bool)>, Coord), Error> {
+    let mut grid = parse_grid(input, |c| char_to_tile(c).map(|tile| (tile, false)))?;
 
-    (grid, start)
+    let starts: Vec<Coord> = grid
+        .enumerate()
+        .filter(|(_, (tile, _))| *tile == Tile::Start)
+        .map(|(coord, _)| coord)
+        .collect();
+    let start = match starts[..] {
+        [start] => start,
+        [] => return Err(Error::Parse("no start tile".to_string())),
+        _ => return Err(Error::Parse("more than one start tile".to_string())),
+    };
+    grid.get_mut(start).unwrap().0 = start_tile(&grid, start)?;
+
+    Ok((grid, start))
 }
 
 #[aoc(day10, part1)]
-fn part1((grid, start): &(Grid<(Tile, bool)>, Coord)) -> u32 {
+fn part1((grid, start): &(Grid<(Tile, bool)>, Coord)) -> u64 {
     let mut grid = Grid::new_iterator(grid.size(), grid.iter().copied());
 
     find_loop(&mut grid, *start) / 2
 }
 
 #[aoc(day10, part2)]
-fn part2((grid, start): &(Grid<(Tile, bool)>, Coord)) -> u32 {
+fn part2((grid, start): &(Grid<(Tile, bool)>, Coord)) -> u64 {
     let mut grid = Grid::new_iterator(grid.size(), grid.iter().copied());
 
     find_loop(&mut grid, *start);
@@ -142,3 +174,48 @@ fn part2((grid, start): &(Grid<(Tile, bool)>, Coord)) -> u32 {
 
     inside_count
 }
+
+pub struct Day10;
+
+impl Solution for Day10 {
+    type Input = (Grid<(Tile, bool)>, Coord);
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input).into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1() {
+        assert_eq!(
+            parse_input("-L|F7\n7S-7|\nL|7||\n-L-J|\nL|-JF").map(|(_, start)| start),
+            Ok(Coord::new(1, 1))
+        );
+        assert_eq!(
+            parse_input(".F7\n.LJ").map(|_| ()),
+            Err(Error::Parse("no start tile".to_string()))
+        );
+        assert_eq!(
+            parse_input("SS\n..").map(|_| ()),
+            Err(Error::Parse("more than one start tile".to_string()))
+        );
+        assert_eq!(
+            parse_input("S-7\n..|").map(|_| ()),
+            Err(Error::Parse(
+                "start doesn't connect to two pipes".to_string()
+            ))
+        );
+    }
+}
@@ -1,99 +1,342 @@
+use crate::error::{checked_sum, Error};
+use crate::grid::parse_grid;
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::Itertools;
 use std::cmp::{max, min};
-use std::collections::HashSet;
-use std::ops::Range;
 
 #[derive(Copy, Clone, Debug)]
-struct Point {
-    row: usize,
-    column: usize,
+pub struct Point {
+    pub row: usize,
+    pub column: usize,
 }
 
 impl Point {
-    fn distance(self, other: Point) -> usize {
+    pub fn distance(self, other: Point) -> usize {
         (max(self.row, other.row) - min(self.row, other.row))
             + (max(self.column, other.column) - min(self.column, other.column))
     }
 }
 
-fn missing_values(range: Range<usize>, present_values: HashSet<usize>) -> Vec<usize> {
-    let mut missing = vec![];
+#[derive(Clone, Debug)]
+pub struct Universe {
+    galaxies: Vec<Point>,
+    rows: usize,
+    columns: usize,
+}
+
+impl Universe {
+    pub fn new(galaxies: Vec<Point>) -> Self {
+        let rows = galaxies
+            .iter()
+            .map(|galaxy| galaxy.row + 1)
+            .max()
+            .unwrap_or(0);
+        let columns = galaxies
+            .iter()
+            .map(|galaxy| galaxy.column + 1)
+            .max()
+            .unwrap_or(0);
+
+        Universe {
+            galaxies,
+            rows,
+            columns,
+        }
+    }
 
-    for i in range {
-        if !present_values.contains(&i) {
-            missing.push(i);
+    // Galaxy positions after every empty row and column is replaced by `factor` empty ones, so a
+    // factor of 1 leaves the universe unchanged.
+    pub fn expanded(&self, factor: usize) -> Result<ExpandedUniverse, Error> {
+        if factor == 0 {
+            return Err(Error::ZeroFactor);
         }
+
+        let row_offsets = expansion_offsets(
+            self.galaxies.iter().map(|galaxy| galaxy.row),
+            self.rows,
+            factor,
+        )?;
+        let column_offsets = expansion_offsets(
+            self.galaxies.iter().map(|galaxy| galaxy.column),
+            self.columns,
+            factor,
+        )?;
+
+        let galaxies = self
+            .galaxies
+            .iter()
+            .map(|galaxy| {
+                Ok(Point {
+                    row: galaxy
+                        .row
+                        .checked_add(row_offsets[galaxy.row])
+                        .ok_or(Error::Overflow)?,
+                    column: galaxy
+                        .column
+                        .checked_add(column_offsets[galaxy.column])
+                        .ok_or(Error::Overflow)?,
+                })
+            })
+            .collect::<Result<Vec<Point>, Error>>()?;
+
+        Ok(ExpandedUniverse { galaxies })
+    }
+
+    pub fn total_distance(&self, factor: usize) -> Result<u64, Error> {
+        self.expanded(factor)?.total_distance()
     }
+}
 
-    missing
+// A universe expanded by one factor, so queries against it share the expansion.
+#[derive(Clone, Debug)]
+pub struct ExpandedUniverse {
+    pub galaxies: Vec<Point>,
 }
 
-fn expand_universe(galaxies: &mut [Point], expansion_size: usize) {
-    const NUM_ROWS: usize = 140;
-    const NUM_COLUMNS: usize = 140;
+impl ExpandedUniverse {
+    // Sum of the distances between every pair of galaxies. The rows and columns contribute
+    // independently, and each is summed in one pass over the sorted coordinates.
+    pub fn total_distance(&self) -> Result<u64, Error> {
+        checked_sum([
+            axis_distance(
+                self.galaxies
+                    .iter()
+                    .map(|galaxy| galaxy.row as u64)
+                    .collect(),
+            )?,
+            axis_distance(
+                self.galaxies
+                    .iter()
+                    .map(|galaxy| galaxy.column as u64)
+                    .collect(),
+            )?,
+        ])
+    }
+
+    // Galaxies are numbered from 1 in reading order, as in the puzzle.
+    pub fn distance(&self, first: usize, second: usize) -> Result<usize, Error> {
+        Ok(self.galaxy(first)?.distance(self.galaxy(second)?))
+    }
 
-    let rows_with_galaxies: HashSet<usize> = galaxies.iter().map(|point| point.row).collect();
-    let rows_without_galaxies = missing_values(0..NUM_ROWS, rows_with_galaxies);
+    // The `k` galaxies closest to the given one as (galaxy, distance), nearest first with ties
+    // broken by galaxy number.
+    pub fn nearest(&self, galaxy: usize, k: usize) -> Result<Vec<(usize, usize)>, Error> {
+        let origin = self.galaxy(galaxy)?;
 
-    let columns_with_galaxies: HashSet<usize> = galaxies.iter().map(|point| point.column).collect();
-    let columns_without_galaxies = missing_values(0..NUM_COLUMNS, columns_with_galaxies);
+        let mut distances: Vec<(usize, usize)> = self
+            .galaxies
+            .iter()
+            .enumerate()
+            .map(|(index, other)| (index + 1, origin.distance(*other)))
+            .filter(|(number, _)| *number != galaxy)
+            .collect();
+        distances.sort_by_key(|&(number, distance)| (distance, number));
+        distances.truncate(k);
 
-    for galaxy in galaxies {
-        galaxy.row += expansion_size
-            * rows_without_galaxies
-                .iter()
-                .filter(|g| *g < &galaxy.row)
-                .count();
-        galaxy.column += expansion_size
-            * columns_without_galaxies
-                .iter()
-                .filter(|g| *g < &galaxy.column)
-                .count();
+        Ok(distances)
+    }
+
+    // Distance between every pair of galaxies, with a header row and column of galaxy numbers.
+    pub fn distance_matrix_csv(&self) -> String {
+        let mut csv = String::new();
+        for number in 1..=self.galaxies.len() {
+            csv += &format!(",{}", number);
+        }
+        csv += "\n";
+
+        for (index, galaxy) in self.galaxies.iter().enumerate() {
+            csv += &(index + 1).to_string();
+            for other in &self.galaxies {
+                csv += &format!(",{}", galaxy.distance(*other));
+            }
+            csv += "\n";
+        }
+
+        csv
+    }
+
+    fn galaxy(&self, galaxy: usize) -> Result<Point, Error> {
+        galaxy
+            .checked_sub(1)
+            .and_then(|index| self.galaxies.get(index))
+            .copied()
+            .ok_or(Error::NoGalaxy {
+                galaxy,
+                count: self.galaxies.len(),
+            })
     }
 }
 
-fn generate_pairs(galaxies: Vec<Point>) -> Vec<(Point, Point)> {
-    galaxies.into_iter().tuple_combinations().collect()
+// How far each line up to `bound` moves when the empty lines before it are expanded.
+fn expansion_offsets(
+    occupied: impl Iterator<Item = usize>,
+    bound: usize,
+    factor: usize,
+) -> Result<Vec<usize>, Error> {
+    let mut is_empty = vec![true; bound];
+    occupied.for_each(|line| is_empty[line] = false);
+
+    let mut offsets = Vec::with_capacity(bound);
+    let mut offset: usize = 0;
+
+    for empty in is_empty {
+        offsets.push(offset);
+        if empty {
+            offset = offset.checked_add(factor - 1).ok_or(Error::Overflow)?;
+        }
+    }
+
+    Ok(offsets)
+}
+
+fn axis_distance(mut values: Vec<u64>) -> Result<u64, Error> {
+    values.sort_unstable();
+
+    let mut total: u64 = 0;
+    let mut preceding: u64 = 0;
+
+    for (index, value) in values.into_iter().enumerate() {
+        // Each value is at least as large as every value before it
+        total = value
+            .checked_mul(index as u64)
+            .map(|scaled| scaled - preceding)
+            .and_then(|distance| total.checked_add(distance))
+            .ok_or(Error::Overflow)?;
+        preceding = preceding.checked_add(value).ok_or(Error::Overflow)?;
+    }
+
+    Ok(total)
 }
 
 #[aoc_generator(day11)]
-fn parse_input(input: &str) -> Vec<Point> {
-    input
-        .lines()
-        .enumerate()
-        .flat_map(|(row, line)| {
-            line.chars().enumerate().filter_map(move |(column, c)| {
-                if c == '#' {
-                    Some(Point { row, column })
-                } else {
-                    None
-                }
+fn parse_input(input: &str) -> Result<Universe, Error> {
+    let grid = parse_grid(input, |c| match c {
+        '#' => Some(true),
+        '.' => Some(false),
+        _ => None,
+    })?;
+
+    Ok(Universe::new(
+        grid.enumerate()
+            .filter(|(_, galaxy)| **galaxy)
+            .map(|(coord, _)| Point {
+                row: coord.y as usize,
+                column: coord.x as usize,
             })
-        })
-        .collect()
+            .collect(),
+    ))
 }
 
 #[aoc(day11, part1)]
-fn part1(galaxies: &[Point]) -> usize {
-    let mut galaxies = galaxies.to_vec();
+fn part1(universe: &Universe) -> Result<u64, Error> {
+    universe.total_distance(2)
+}
 
-    expand_universe(&mut galaxies, 1);
+#[aoc(day11, part2)]
+fn part2(universe: &Universe) -> Result<u64, Error> {
+    universe.total_distance(1000000)
+}
 
-    generate_pairs(galaxies)
-        .iter()
-        .map(|(a, b)| a.distance(*b))
-        .sum()
+pub struct Day11;
+
+impl Solution for Day11 {
+    type Input = Universe;
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
 }
 
-#[aoc(day11, part2)]
-fn part2(galaxies: &[Point]) -> usize {
-    let mut galaxies = galaxies.to_vec();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static INPUT: &str = concat!(
+        "...#......\n",
+        ".......#..\n",
+        "#.........\n",
+        "..........\n",
+        "......#...\n",
+        ".#........\n",
+        ".........#\n",
+        "..........\n",
+        ".......#..\n",
+        "#...#.....\n",
+    );
+
+    #[test]
+    fn test1() {
+        let universe = parse_input(INPUT).unwrap();
+
+        assert_eq!(universe.total_distance(2), Ok(374));
+        assert_eq!(universe.total_distance(10), Ok(1030));
+        assert_eq!(universe.total_distance(100), Ok(8410));
+    }
+
+    #[test]
+    fn test2() {
+        let universe = parse_input(INPUT).unwrap();
+        let galaxies = universe.expanded(1).unwrap().galaxies;
 
-    expand_universe(&mut galaxies, 1000000 - 1);
+        let pairwise: usize = (0..galaxies.len())
+            .flat_map(|i| (i + 1..galaxies.len()).map(move |j| (i, j)))
+            .map(|(i, j)| galaxies[i].distance(galaxies[j]))
+            .sum();
+
+        assert_eq!(universe.total_distance(1), Ok(pairwise as u64));
+        assert_eq!(
+            Universe::new(vec![Point { row: 0, column: 0 }; 2]).total_distance(usize::MAX),
+            Ok(0)
+        );
+        assert_eq!(
+            Universe::new(vec![Point { row: 2, column: 0 }]).total_distance(usize::MAX),
+            Err(Error::Overflow)
+        );
+    }
 
-    generate_pairs(galaxies)
-        .iter()
-        .map(|(a, b)| a.distance(*b))
-        .sum()
+    #[test]
+    fn test3() {
+        let universe = parse_input(INPUT).unwrap();
+        let expanded = universe.expanded(2).unwrap();
+
+        assert_eq!(expanded.distance(5, 9), Ok(9));
+        assert_eq!(expanded.distance(1, 7), Ok(15));
+        assert_eq!(expanded.distance(3, 6), Ok(17));
+        assert_eq!(expanded.distance(8, 9), Ok(5));
+        assert_eq!(universe.expanded(1).unwrap().distance(8, 9), Ok(4));
+        assert_eq!(expanded.nearest(8, 3), Ok(vec![(9, 5), (5, 6), (3, 9)]));
+        assert_eq!(
+            expanded.distance(0, 9),
+            Err(Error::NoGalaxy {
+                galaxy: 0,
+                count: 9
+            })
+        );
+        assert_eq!(
+            expanded.nearest(10, 3),
+            Err(Error::NoGalaxy {
+                galaxy: 10,
+                count: 9
+            })
+        );
+        assert_eq!(universe.total_distance(0), Err(Error::ZeroFactor));
+    }
+
+    #[test]
+    fn test4() {
+        let universe = parse_input("#.#\n...\n..#").unwrap();
+
+        assert_eq!(
+            universe.expanded(3).unwrap().distance_matrix_csv(),
+            ",1,2,3\n1,0,4,8\n2,4,0,4\n3,8,4,0\n"
+        );
+    }
 }
@@ -1,5 +1,6 @@
+use crate::error::{checked_sum, parse_all, Error};
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
-use cached::proc_macro::cached;
 use nom::branch::alt;
 use nom::bytes::complete::tag;
 use nom::character::complete::newline;
@@ -7,71 +8,205 @@ use nom::multi::{many1, separated_list1};
 use nom::sequence::separated_pair;
 use nom::IResult;
 
-type ConditionRecord = (String, Vec<u64>);
-
-#[cached]
-fn count_possible_condition_records(
-    conditions: String,
-    mut broken_counts: Vec<u64>,
-    mut broken_seen: u64,
-) -> u64 {
-    if conditions.is_empty() {
-        if broken_counts.is_empty() || broken_counts == [broken_seen] {
-            return 1;
-        } else {
-            return 0;
+pub type ConditionRecord = (String, Vec<u64>);
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Impossibility {
+    UnplaceableGroup { group: usize, size: u64 },
+    UnexpectedBroken { position: usize },
+}
+
+// runs[position] is the number of consecutive cells from position that could be broken, so a
+// group fits when its run is long enough and the cell after it can be working.
+fn possibly_broken_runs(conditions: &[u8]) -> Vec<usize> {
+    let mut runs = vec![0; conditions.len() + 1];
+
+    for position in (0..conditions.len()).rev() {
+        if conditions[position] != b'.' {
+            runs[position] = runs[position + 1] + 1;
         }
     }
 
-    if broken_counts.is_empty() {
-        if conditions.find('#').is_some() {
-            return 0;
-        } else {
-            return 1;
+    runs
+}
+
+fn group_fits(conditions: &[u8], runs: &[usize], position: usize, size: u64) -> bool {
+    size as usize <= runs[position] && conditions.get(position + size as usize) != Some(&b'#')
+}
+
+// Bottom-up table over suffixes: ways[position][group] is the number of arrangements of
+// conditions[position..] that produce exactly broken_counts[group..].
+pub struct ArrangementTable {
+    ways: Vec<u64>,
+    groups: usize,
+    runs: Vec<usize>,
+}
+
+impl ArrangementTable {
+    pub fn new(conditions: &[u8], broken_counts: &[u64]) -> Result<Self, Error> {
+        let length = conditions.len();
+        let groups = broken_counts.len();
+
+        let mut table = Self {
+            ways: vec![0; (length + 1) * (groups + 1)],
+            groups,
+            runs: possibly_broken_runs(conditions),
+        };
+        *table.get_mut(length, groups) = 1;
+
+        for position in (0..length).rev() {
+            let condition = conditions[position];
+
+            for group in 0..=groups {
+                let mut count: u64 = 0;
+
+                if condition != b'#' {
+                    count = table.get(position + 1, group);
+                }
+
+                if let Some(&size) = broken_counts.get(group) {
+                    if condition != b'.' && group_fits(conditions, &table.runs, position, size) {
+                        let next = (position + size as usize + 1).min(length);
+                        count = count
+                            .checked_add(table.get(next, group + 1))
+                            .ok_or(Error::Overflow)?;
+                    }
+                }
+
+                *table.get_mut(position, group) = count;
+            }
         }
+
+        Ok(table)
     }
 
-    match conditions.chars().next().unwrap() {
-        '.' => {
-            if broken_seen > 0 {
-                if broken_seen == *broken_counts.first().expect("checked not empty above") {
-                    broken_counts.remove(0);
-                    broken_seen = 0;
-                } else {
-                    return 0;
-                }
+    pub fn get(&self, position: usize, group: usize) -> u64 {
+        self.ways[position * (self.groups + 1) + group]
+    }
+
+    fn get_mut(&mut self, position: usize, group: usize) -> &mut u64 {
+        &mut self.ways[position * (self.groups + 1) + group]
+    }
+}
+
+pub fn count_possible_condition_records(
+    conditions: &[u8],
+    broken_counts: &[u64],
+) -> Result<u64, Error> {
+    Ok(ArrangementTable::new(conditions, broken_counts)?.get(0, 0))
+}
+
+struct ArrangementWalker<'a> {
+    conditions: &'a [u8],
+    broken_counts: &'a [u64],
+    table: ArrangementTable,
+    limit: usize,
+    current: Vec<u8>,
+    arrangements: Vec<String>,
+}
+
+impl ArrangementWalker<'_> {
+    fn walk(&mut self, position: usize, group: usize) {
+        if self.arrangements.len() >= self.limit || self.table.get(position, group) == 0 {
+            return;
+        }
+
+        if position == self.conditions.len() {
+            self.arrangements
+                .push(String::from_utf8(self.current.clone()).unwrap());
+            return;
+        }
+
+        let length = self.current.len();
+
+        if let Some(&size) = self.broken_counts.get(group) {
+            if self.conditions[position] != b'.'
+                && group_fits(self.conditions, &self.table.runs, position, size)
+            {
+                let end = position + size as usize;
+                let next = (end + 1).min(self.conditions.len());
+
+                self.current
+                    .extend(std::iter::repeat_n(b'#', end - position));
+                self.current.extend(std::iter::repeat_n(b'.', next - end));
+                self.walk(next, group + 1);
+                self.current.truncate(length);
             }
+        }
 
-            count_possible_condition_records(
-                conditions[1..].to_string(),
-                broken_counts,
-                broken_seen,
-            )
+        if self.conditions[position] != b'#' {
+            self.current.push(b'.');
+            self.walk(position + 1, group);
+            self.current.truncate(length);
         }
-        '#' => {
-            broken_seen += 1;
+    }
+}
 
-            if broken_seen > *broken_counts.first().expect("checked not empty above") {
-                return 0;
+// Lists up to `limit` concrete arrangements, placing groups as early as possible first.
+pub fn list_arrangements(
+    conditions: &[u8],
+    broken_counts: &[u64],
+    limit: usize,
+) -> Result<Vec<String>, Error> {
+    let mut walker = ArrangementWalker {
+        conditions,
+        broken_counts,
+        table: ArrangementTable::new(conditions, broken_counts)?,
+        limit,
+        current: vec![],
+        arrangements: vec![],
+    };
+
+    walker.walk(0, 0);
+
+    Ok(walker.arrangements)
+}
+
+// Explains why a record has no arrangements by placing groups left to right and reporting the
+// first one with nowhere to go, or a broken spring left over once every group is placed.
+pub fn explain_impossible(conditions: &[u8], broken_counts: &[u64]) -> Option<Impossibility> {
+    let length = conditions.len();
+    let runs = possibly_broken_runs(conditions);
+
+    // reachable[position] is true when conditions[..position] can hold the groups placed so far
+    // and the next group may start at position.
+    let mut reachable = vec![false; length + 1];
+    reachable[0] = true;
+
+    let extend_through_working = |reachable: &mut Vec<bool>| {
+        for position in 1..=length {
+            if reachable[position - 1] && conditions[position - 1] != b'#' {
+                reachable[position] = true;
             }
+        }
+    };
+    extend_through_working(&mut reachable);
 
-            count_possible_condition_records(
-                conditions[1..].to_string(),
-                broken_counts,
-                broken_seen,
-            )
+    for (group, &size) in broken_counts.iter().enumerate() {
+        let mut next = vec![false; length + 1];
+
+        for position in (0..length).filter(|&position| reachable[position]) {
+            if group_fits(conditions, &runs, position, size) {
+                next[(position + size as usize + 1).min(length)] = true;
+            }
         }
-        '?' => {
-            let mut good_conditions = conditions.clone();
-            good_conditions.replace_range(0..1, ".");
-            let mut broken_conditions = conditions;
-            broken_conditions.replace_range(0..1, "#");
-
-            count_possible_condition_records(good_conditions, broken_counts.clone(), broken_seen)
-                + count_possible_condition_records(broken_conditions, broken_counts, broken_seen)
+
+        if !next.contains(&true) {
+            return Some(Impossibility::UnplaceableGroup { group, size });
         }
-        _ => panic!(),
+
+        extend_through_working(&mut next);
+        reachable = next;
     }
+
+    if reachable[length] {
+        return None;
+    }
+
+    let earliest = reachable.iter().position(|&reachable| reachable)?;
+    let position = earliest + conditions[earliest..].iter().position(|&c| c == b'#')?;
+
+    Some(Impossibility::UnexpectedBroken { position })
 }
 
 fn parse_conditions(input: &str) -> IResult<&str, String> {
@@ -89,23 +224,25 @@ fn parse_row(input: &str) -> IResult<&str, ConditionRecord> {
 }
 
 #[aoc_generator(day12)]
-fn parse_input(input: &str) -> Vec<ConditionRecord> {
+fn parse_input(input: &str) -> Result<Vec<ConditionRecord>, Error> {
     // let input = "???.### 1,1,3\n.??..??...?##. 1,1,3\n?#?#?#?#?#?#?#? 1,3,1,6\n????.#...#... 4,1,1\n????.######..#####. 1,6,5\n?###???????? 3,2,1";
-    separated_list1(newline, parse_row)(input).unwrap().1
+    parse_all(separated_list1(newline, parse_row), input)
 }
 
 #[aoc(day12, part1)]
-fn part1(records: &[ConditionRecord]) -> u64 {
-    records
+fn part1(records: &[ConditionRecord]) -> Result<u64, Error> {
+    let counts = records
         .iter()
         .map(|(conditions, broken_counts)| {
-            count_possible_condition_records(conditions.clone(), broken_counts.clone(), 0)
+            count_possible_condition_records(conditions.as_bytes(), broken_counts)
         })
-        .sum()
+        .collect::<Result<Vec<u64>, Error>>()?;
+
+    checked_sum(counts)
 }
 
 #[aoc(day12, part2)]
-fn part2(records: &[ConditionRecord]) -> u64 {
+fn part2(records: &[ConditionRecord]) -> Result<u64, Error> {
     const REPETITIONS: usize = 5;
 
     let records: Vec<ConditionRecord> = records
@@ -128,12 +265,32 @@ fn part2(records: &[ConditionRecord]) -> u64 {
         })
         .collect();
 
-    records
+    let counts = records
         .iter()
         .map(|(conditions, broken_counts)| {
-            count_possible_condition_records(conditions.clone(), broken_counts.clone(), 0)
+            count_possible_condition_records(conditions.as_bytes(), broken_counts)
         })
-        .sum()
+        .collect::<Result<Vec<u64>, Error>>()?;
+
+    checked_sum(counts)
+}
+
+pub struct Day12;
+
+impl Solution for Day12 {
+    type Input = Vec<ConditionRecord>;
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
 }
 
 #[cfg(test)]
@@ -143,48 +300,137 @@ mod tests {
     #[test]
     fn test1() {
         let input = "???.### 1,1,3";
-        let (conditions, broken_counts) = &parse_input(input)[0];
-        let count = count_possible_condition_records(conditions.clone(), broken_counts.clone(), 0);
+        let (conditions, broken_counts) = &parse_input(input).unwrap()[0];
+        let count = count_possible_condition_records(conditions.as_bytes(), broken_counts).unwrap();
+        let arrangements =
+            list_arrangements(conditions.as_bytes(), broken_counts, usize::MAX).unwrap();
         assert_eq!(count, 1);
+        assert_eq!(arrangements, vec!["#.#.###"]);
     }
 
     #[test]
     fn test2() {
         let input = ".??..??...?##. 1,1,3";
-        let (conditions, broken_counts) = &parse_input(input)[0];
-        let count = count_possible_condition_records(conditions.clone(), broken_counts.clone(), 0);
+        let (conditions, broken_counts) = &parse_input(input).unwrap()[0];
+        let count = count_possible_condition_records(conditions.as_bytes(), broken_counts).unwrap();
+        let arrangements =
+            list_arrangements(conditions.as_bytes(), broken_counts, usize::MAX).unwrap();
         assert_eq!(count, 4);
+        assert_eq!(
+            arrangements,
+            vec![
+                ".#...#....###.",
+                ".#....#...###.",
+                "..#..#....###.",
+                "..#...#...###.",
+            ]
+        );
     }
 
     #[test]
     fn test3() {
         let input = "?#?#?#?#?#?#?#? 1,3,1,6";
-        let (conditions, broken_counts) = &parse_input(input)[0];
-        let count = count_possible_condition_records(conditions.clone(), broken_counts.clone(), 0);
+        let (conditions, broken_counts) = &parse_input(input).unwrap()[0];
+        let count = count_possible_condition_records(conditions.as_bytes(), broken_counts).unwrap();
+        let arrangements =
+            list_arrangements(conditions.as_bytes(), broken_counts, usize::MAX).unwrap();
         assert_eq!(count, 1);
+        assert_eq!(arrangements, vec![".#.###.#.######"]);
     }
 
     #[test]
     fn test4() {
         let input = "????.#...#... 4,1,1";
-        let (conditions, broken_counts) = &parse_input(input)[0];
-        let count = count_possible_condition_records(conditions.clone(), broken_counts.clone(), 0);
+        let (conditions, broken_counts) = &parse_input(input).unwrap()[0];
+        let count = count_possible_condition_records(conditions.as_bytes(), broken_counts).unwrap();
+        let arrangements =
+            list_arrangements(conditions.as_bytes(), broken_counts, usize::MAX).unwrap();
         assert_eq!(count, 1);
+        assert_eq!(arrangements, vec!["####.#...#..."]);
     }
 
     #[test]
     fn test5() {
         let input = "????.######..#####. 1,6,5";
-        let (conditions, broken_counts) = &parse_input(input)[0];
-        let count = count_possible_condition_records(conditions.clone(), broken_counts.clone(), 0);
+        let (conditions, broken_counts) = &parse_input(input).unwrap()[0];
+        let count = count_possible_condition_records(conditions.as_bytes(), broken_counts).unwrap();
+        let arrangements =
+            list_arrangements(conditions.as_bytes(), broken_counts, usize::MAX).unwrap();
         assert_eq!(count, 4);
+        assert_eq!(
+            arrangements,
+            vec![
+                "#....######..#####.",
+                ".#...######..#####.",
+                "..#..######..#####.",
+                "...#.######..#####.",
+            ]
+        );
     }
 
     #[test]
     fn test6() {
         let input = "?###???????? 3,2,1";
-        let (conditions, broken_counts) = &parse_input(input)[0];
-        let count = count_possible_condition_records(conditions.clone(), broken_counts.clone(), 0);
+        let (conditions, broken_counts) = &parse_input(input).unwrap()[0];
+        let count = count_possible_condition_records(conditions.as_bytes(), broken_counts).unwrap();
+        let arrangements =
+            list_arrangements(conditions.as_bytes(), broken_counts, usize::MAX).unwrap();
         assert_eq!(count, 10);
+        assert_eq!(
+            arrangements,
+            vec![
+                ".###.##.#...",
+                ".###.##..#..",
+                ".###.##...#.",
+                ".###.##....#",
+                ".###..##.#..",
+                ".###..##..#.",
+                ".###..##...#",
+                ".###...##.#.",
+                ".###...##..#",
+                ".###....##.#",
+            ]
+        );
+    }
+
+    #[test]
+    fn test7() {
+        let input = concat!(
+            "???.### 1,1,3\n",
+            ".??..??...?##. 1,1,3\n",
+            "?#?#?#?#?#?#?#? 1,3,1,6\n",
+            "????.#...#... 4,1,1\n",
+            "????.######..#####. 1,6,5\n",
+            "?###???????? 3,2,1",
+        );
+        assert_eq!(part2(&parse_input(input).unwrap()), Ok(525152));
+    }
+
+    #[test]
+    fn test8() {
+        let input = "?###???????? 3,2,1";
+        let (conditions, broken_counts) = &parse_input(input).unwrap()[0];
+        let arrangements = list_arrangements(conditions.as_bytes(), broken_counts, 2).unwrap();
+        assert_eq!(arrangements, vec![".###.##.#...", ".###.##..#.."]);
+    }
+
+    #[test]
+    fn test9() {
+        assert_eq!(explain_impossible(b"???.###", &[1, 1, 3]), None);
+        assert_eq!(
+            explain_impossible(b"???.###", &[1, 1, 4]),
+            Some(Impossibility::UnplaceableGroup { group: 2, size: 4 })
+        );
+        assert_eq!(
+            explain_impossible(b"??#.#", &[3]),
+            Some(Impossibility::UnexpectedBroken { position: 4 })
+        );
+    }
+
+    #[test]
+    fn test10() {
+        assert!(parse_input("???.### 1,1,3").is_ok());
+        assert!(parse_input("??x.### 1,1,3").is_err());
+        assert!(parse_input("???.### 1,,3").is_err());
     }
 }
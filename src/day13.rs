@@ -1,19 +1,22 @@
+use crate::error::{checked_sum, parse_all, Error};
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
 use grid_2d::{Grid, Size};
 use nom::branch::alt;
 use nom::bytes::complete::tag;
 use nom::character::complete::newline;
+use nom::combinator::verify;
 use nom::multi::{many1, separated_list1};
 use nom::IResult;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-enum Axis {
+pub enum Axis {
     Vertical,
     Horizontal,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-enum Terrain {
+pub enum Terrain {
     Ash,
     Rock,
 }
@@ -28,75 +31,75 @@ impl From<char> for Terrain {
     }
 }
 
-fn calculate_row_inaccuracies(row: &[Terrain]) -> Vec<usize> {
-    (1..row.len())
-        .map(|index| {
-            row[..index]
-                .iter()
-                .rev()
-                .zip(row[index..].iter())
-                .filter(|(a, b)| a != b)
-                .count()
-        })
-        .collect()
+// Each row or column is packed into bitmask chunks with a set bit for every rock, so
+// the number of mismatched cells between two lines is the popcount of their XOR.
+type Chunk = u128;
+type Line = Vec<Chunk>;
+
+fn encode_line(cells: impl Iterator<Item = Terrain>) -> Line {
+    let mut line = vec![];
+
+    for (index, cell) in cells.enumerate() {
+        let bit = index % Chunk::BITS as usize;
+        if bit == 0 {
+            line.push(0);
+        }
+        if cell == Terrain::Rock {
+            *line.last_mut().unwrap() |= 1 << bit;
+        }
+    }
+
+    line
 }
 
-fn calculate_horizontal_inaccuracies(grid: &Grid<Terrain>) -> Vec<usize> {
-    let width = grid.width() as usize;
+fn mismatches(a: &Line, b: &Line) -> usize {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a ^ b).count_ones() as usize)
+        .sum()
+}
 
+fn encode_rows(grid: &Grid<Terrain>) -> Vec<Line> {
     grid.rows()
-        .map(calculate_row_inaccuracies)
-        .fold(vec![0; width - 1], |mut acc, v| {
-            acc.iter_mut()
-                .zip(v.iter())
-                .for_each(|(acc, elem)| *acc += elem);
-            acc
-        })
+        .map(|row| encode_line(row.iter().copied()))
+        .collect()
 }
 
-fn calculate_vertical_inaccuracies(grid: &Grid<Terrain>) -> Vec<usize> {
+fn encode_columns(grid: &Grid<Terrain>) -> Vec<Line> {
     let width = grid.width() as usize;
-    let height = grid.height() as usize;
 
     (0..width)
-        .map(|column_index| {
-            let column: Vec<Terrain> = grid
-                .iter()
-                .skip(column_index)
-                .step_by(width)
-                .copied()
-                .collect();
+        .map(|column_index| encode_line(grid.iter().skip(column_index).step_by(width).copied()))
+        .collect()
+}
 
-            calculate_row_inaccuracies(&column)
-        })
-        .fold(vec![0; height - 1], |mut acc, v| {
-            acc.iter_mut()
-                .zip(v.iter())
-                .for_each(|(acc, elem)| *acc += elem);
-            acc
+fn calculate_line_inaccuracies(lines: &[Line]) -> Vec<usize> {
+    (1..lines.len())
+        .map(|index| {
+            lines[..index]
+                .iter()
+                .rev()
+                .zip(lines[index..].iter())
+                .map(|(a, b)| mismatches(a, b))
+                .sum()
         })
+        .collect()
 }
 
-fn calculate_inaccuracies(grid: &Grid<Terrain>) -> Vec<(usize, Axis, usize)> {
-    let mut inaccuracies = vec![];
-
-    for (index, count) in calculate_horizontal_inaccuracies(grid)
+pub fn calculate_inaccuracies(grid: &Grid<Terrain>) -> Vec<(usize, Axis, usize)> {
+    let columns = calculate_line_inaccuracies(&encode_columns(grid))
         .into_iter()
         .enumerate()
-    {
-        inaccuracies.push((index + 1, Axis::Vertical, count));
-    }
-    for (index, count) in calculate_vertical_inaccuracies(grid)
+        .map(|(index, count)| (index + 1, Axis::Vertical, count));
+    let rows = calculate_line_inaccuracies(&encode_rows(grid))
         .into_iter()
         .enumerate()
-    {
-        inaccuracies.push((index + 1, Axis::Horizontal, count));
-    }
+        .map(|(index, count)| (index + 1, Axis::Horizontal, count));
 
-    inaccuracies
+    columns.chain(rows).collect()
 }
 
-fn find_mirror(grid: &Grid<Terrain>, inaccuracy_count: usize) -> (usize, Axis) {
+pub fn find_mirror(grid: &Grid<Terrain>, inaccuracy_count: usize) -> (usize, Axis) {
     let mirror: Vec<(usize, Axis)> = calculate_inaccuracies(grid)
         .into_iter()
         .filter_map(|(index, axis, count)| {
@@ -115,11 +118,11 @@ fn find_mirror(grid: &Grid<Terrain>, inaccuracy_count: usize) -> (usize, Axis) {
     mirror[0]
 }
 
-fn find_valid_mirror(grid: &Grid<Terrain>) -> (usize, Axis) {
+pub fn find_valid_mirror(grid: &Grid<Terrain>) -> (usize, Axis) {
     find_mirror(grid, 0)
 }
 
-fn find_smudged_mirror(grid: &Grid<Terrain>) -> (usize, Axis) {
+pub fn find_smudged_mirror(grid: &Grid<Terrain>) -> (usize, Axis) {
     find_mirror(grid, 1)
 }
 
@@ -136,7 +139,10 @@ fn parse_grid_line(input: &str) -> IResult<&str, Vec<Terrain>> {
 }
 
 fn parse_grid(input: &str) -> IResult<&str, Grid<Terrain>> {
-    let (input, lines) = separated_list1(newline, parse_grid_line)(input)?;
+    let (input, lines) = verify(
+        separated_list1(newline, parse_grid_line),
+        |lines: &Vec<Vec<Terrain>>| lines.iter().all(|line| line.len() == lines[0].len()),
+    )(input)?;
 
     Ok((
         input,
@@ -148,32 +154,55 @@ fn parse_grid(input: &str) -> IResult<&str, Grid<Terrain>> {
 }
 
 #[aoc_generator(day13)]
-fn parse_input(input: &str) -> Vec<Grid<Terrain>> {
-    let (_, grids) = separated_list1(tag("\n\n"), parse_grid)(input).unwrap();
+fn parse_input(input: &str) -> Result<Vec<Grid<Terrain>>, Error> {
+    let grids = parse_all(separated_list1(tag("\n\n"), parse_grid), input)?;
+
+    Ok(grids)
+}
 
-    grids
+fn summarize(mirror: (usize, Axis)) -> Result<u64, Error> {
+    match mirror {
+        (line, Axis::Vertical) => Ok(line as u64),
+        (line, Axis::Horizontal) => (line as u64).checked_mul(100).ok_or(Error::Overflow),
+    }
 }
 
 #[aoc(day13, part1)]
-fn part1(grids: &[Grid<Terrain>]) -> usize {
-    grids
+fn part1(grids: &[Grid<Terrain>]) -> Result<u64, Error> {
+    let summaries = grids
         .iter()
-        .map(|grid| match find_valid_mirror(grid) {
-            (line, Axis::Vertical) => line,
-            (line, Axis::Horizontal) => 100 * line,
-        })
-        .sum()
+        .map(|grid| summarize(find_valid_mirror(grid)))
+        .collect::<Result<Vec<u64>, Error>>()?;
+
+    checked_sum(summaries)
 }
 
 #[aoc(day13, part2)]
-fn part2(grids: &[Grid<Terrain>]) -> usize {
-    grids
+fn part2(grids: &[Grid<Terrain>]) -> Result<u64, Error> {
+    let summaries = grids
         .iter()
-        .map(|grid| match find_smudged_mirror(grid) {
-            (line, Axis::Vertical) => line,
-            (line, Axis::Horizontal) => 100 * line,
-        })
-        .sum()
+        .map(|grid| summarize(find_smudged_mirror(grid)))
+        .collect::<Result<Vec<u64>, Error>>()?;
+
+    checked_sum(summaries)
+}
+
+pub struct Day13;
+
+impl Solution for Day13 {
+    type Input = Vec<Grid<Terrain>>;
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
 }
 
 #[cfg(test)]
@@ -191,7 +220,7 @@ mod tests {
             "..##..##.\n",
             "#.#.##.#.\n",
         );
-        let grid = parse_input(input);
+        let grid = parse_input(input).unwrap();
         let (mirror_line, axis) = find_valid_mirror(&grid[0]);
         assert_eq!(mirror_line, 5);
         assert_eq!(axis, Axis::Vertical);
@@ -208,9 +237,57 @@ mod tests {
             "..##..###\n",
             "#....#..#\n",
         );
-        let grid = parse_input(input);
+        let grid = parse_input(input).unwrap();
         let (mirror_line, axis) = find_valid_mirror(&grid[0]);
         assert_eq!(mirror_line, 4);
         assert_eq!(axis, Axis::Horizontal);
     }
+
+    #[test]
+    fn test3() {
+        let input = concat!(
+            "#.##..##.\n",
+            "..#.##.#.\n",
+            "##......#\n",
+            "##......#\n",
+            "..#.##.#.\n",
+            "..##..##.\n",
+            "#.#.##.#.\n",
+            "\n",
+            "#...##..#\n",
+            "#....#..#\n",
+            "..##..###\n",
+            "#####.##.\n",
+            "#####.##.\n",
+            "..##..###\n",
+            "#....#..#\n",
+        );
+        let grids = parse_input(input).unwrap();
+        assert_eq!(find_smudged_mirror(&grids[0]), (3, Axis::Horizontal));
+        assert_eq!(find_smudged_mirror(&grids[1]), (1, Axis::Horizontal));
+    }
+
+    #[test]
+    fn test4() {
+        // Rows wider than a single chunk, mirrored down the middle
+        let rows: Vec<String> = (0..3)
+            .map(|row: u32| {
+                let half: String = (0..65)
+                    .map(|column: u32| match (column + row).count_ones() % 2 {
+                        0 => '#',
+                        _ => '.',
+                    })
+                    .collect();
+                half.clone() + &half.chars().rev().collect::<String>()
+            })
+            .collect();
+        let wide = parse_input(&rows.join("\n")).unwrap();
+        assert_eq!(find_valid_mirror(&wide[0]), (65, Axis::Vertical));
+
+        let columns: Vec<String> = (0..130)
+            .map(|column| rows.iter().map(|row| &row[column..=column]).collect())
+            .collect();
+        let tall = parse_input(&columns.join("\n")).unwrap();
+        assert_eq!(find_valid_mirror(&tall[0]), (65, Axis::Horizontal));
+    }
 }
@@ -1,21 +1,26 @@
+use crate::error::{checked_product, checked_sum, Error};
+use crate::grid::parse_grid;
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
-use grid_2d::{Grid, Size};
+use grid_2d::Grid;
 use std::collections::HashMap;
 
 #[derive(Copy, Clone, Hash, Debug, Eq, PartialEq)]
-enum Tile {
+pub enum Tile {
     Round,
     Cube,
     Empty,
 }
 
-impl From<char> for Tile {
-    fn from(c: char) -> Self {
+impl TryFrom<char> for Tile {
+    type Error = Error;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
         match c {
-            'O' => Tile::Round,
-            '#' => Tile::Cube,
-            '.' => Tile::Empty,
-            _ => panic!(),
+            'O' => Ok(Tile::Round),
+            '#' => Ok(Tile::Cube),
+            '.' => Ok(Tile::Empty),
+            _ => Err(Error::Parse(format!("unexpected tile {:?}", c))),
         }
     }
 }
@@ -71,7 +76,7 @@ fn tilt<'a>(
     dest.zip(new_row.iter()).for_each(|(old, new)| *old = *new);
 }
 
-fn tilt_north(grid: &Grid<Tile>) -> Grid<Tile> {
+pub fn tilt_north(grid: &Grid<Tile>) -> Grid<Tile> {
     let width: usize = grid.width().try_into().unwrap();
     let mut tilted = Grid::new_copy(grid.size(), Tile::Empty);
 
@@ -85,7 +90,7 @@ fn tilt_north(grid: &Grid<Tile>) -> Grid<Tile> {
     tilted
 }
 
-fn tilt_west(grid: &Grid<Tile>) -> Grid<Tile> {
+pub fn tilt_west(grid: &Grid<Tile>) -> Grid<Tile> {
     let mut tilted = Grid::new_copy(grid.size(), Tile::Empty);
 
     for (grid_row, tilted_row) in grid.rows().zip(tilted.rows_mut()) {
@@ -95,7 +100,7 @@ fn tilt_west(grid: &Grid<Tile>) -> Grid<Tile> {
     tilted
 }
 
-fn tilt_south(grid: &Grid<Tile>) -> Grid<Tile> {
+pub fn tilt_south(grid: &Grid<Tile>) -> Grid<Tile> {
     let width: usize = grid.width().try_into().unwrap();
     let mut tilted = Grid::new_copy(grid.size(), Tile::Empty);
 
@@ -109,7 +114,7 @@ fn tilt_south(grid: &Grid<Tile>) -> Grid<Tile> {
     tilted
 }
 
-fn tilt_east(grid: &Grid<Tile>) -> Grid<Tile> {
+pub fn tilt_east(grid: &Grid<Tile>) -> Grid<Tile> {
     let mut tilted = Grid::new_copy(grid.size(), Tile::Empty);
 
     for (grid_row, tilted_row) in grid.rows().zip(tilted.rows_mut()) {
@@ -119,11 +124,11 @@ fn tilt_east(grid: &Grid<Tile>) -> Grid<Tile> {
     tilted
 }
 
-fn spin_cycle(grid: &Grid<Tile>) -> Grid<Tile> {
+pub fn spin_cycle(grid: &Grid<Tile>) -> Grid<Tile> {
     tilt_east(&tilt_south(&tilt_west(&tilt_north(grid))))
 }
 
-fn run_cycles(mut grid: Grid<Tile>, cycle_count: usize) -> Grid<Tile> {
+pub fn run_cycles(mut grid: Grid<Tile>, cycle_count: usize) -> Grid<Tile> {
     let mut grids_seen: HashMap<Grid<Tile>, usize> = HashMap::new();
 
     for index in 0..cycle_count {
@@ -148,40 +153,56 @@ fn run_cycles(mut grid: Grid<Tile>, cycle_count: usize) -> Grid<Tile> {
     grid
 }
 
-fn calculate_load(grid: &Grid<Tile>) -> usize {
-    grid.rows()
+pub fn calculate_load(grid: &Grid<Tile>) -> Result<u64, Error> {
+    let loads = grid
+        .rows()
         .rev()
         .enumerate()
-        .map(|(index, row)| row.iter().filter(|tile| *tile == &Tile::Round).count() * (index + 1))
-        .sum()
+        .map(|(index, row)| {
+            let rocks = row.iter().filter(|tile| *tile == &Tile::Round).count() as u64;
+            checked_product([rocks, index as u64 + 1])
+        })
+        .collect::<Result<Vec<u64>, Error>>()?;
+
+    checked_sum(loads)
 }
 
 #[aoc_generator(day14)]
-fn parse_input(input: &str) -> Grid<Tile> {
-    let width = input.chars().position(|c| c == '\n').unwrap() as u32;
-    let height = input.chars().step_by(width as usize + 1).count() as u32;
-
-    Grid::new_iterator(
-        Size::new(width, height),
-        input
-            .chars()
-            .filter_map(|c| if c == '\n' { None } else { Some(Tile::from(c)) }),
-    )
+fn parse_input(input: &str) -> Result<Grid<Tile>, Error> {
+    parse_grid(input, |c| Tile::try_from(c).ok())
 }
 
 #[aoc(day14, part1)]
-fn part1(grid: &Grid<Tile>) -> usize {
+fn part1(grid: &Grid<Tile>) -> Result<u64, Error> {
     let tilted = tilt_north(grid);
     calculate_load(&tilted)
 }
 
 #[aoc(day14, part2)]
-fn part2(grid: &Grid<Tile>) -> usize {
+fn part2(grid: &Grid<Tile>) -> Result<u64, Error> {
     let grid = clone(grid);
 
     calculate_load(&run_cycles(grid, 1000000000))
 }
 
+pub struct Day14;
+
+impl Solution for Day14 {
+    type Input = Grid<Tile>;
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -227,29 +248,29 @@ mod tests {
 
     #[test]
     fn test1() {
-        let grid = parse_input(INPUT);
+        let grid = parse_input(INPUT).unwrap();
 
-        assert_eq!(tilt_north(&grid), parse_input(TILTED));
+        assert_eq!(tilt_north(&grid), parse_input(TILTED).unwrap());
     }
 
     #[test]
     fn test2() {
-        let tilted = parse_input(TILTED);
+        let tilted = parse_input(TILTED).unwrap();
 
-        assert_eq!(calculate_load(&tilted), 136);
+        assert_eq!(calculate_load(&tilted), Ok(136));
     }
 
     #[test]
     fn test3() {
-        let grid = parse_input(INPUT);
+        let grid = parse_input(INPUT).unwrap();
 
-        assert_eq!(spin_cycle(&grid), parse_input(CYCLED));
+        assert_eq!(spin_cycle(&grid), parse_input(CYCLED).unwrap());
     }
 
     #[test]
     fn test4() {
-        let grid = parse_input(INPUT);
+        let grid = parse_input(INPUT).unwrap();
 
-        assert_eq!(calculate_load(&run_cycles(grid, 1000000000)), 64);
+        assert_eq!(calculate_load(&run_cycles(grid, 1000000000)), Ok(64));
     }
 }
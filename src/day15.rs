@@ -1,16 +1,61 @@
+use crate::error::{checked_product, checked_sum, Error};
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::fmt;
 use std::num::Wrapping;
 
-type Lens = (String, u8);
-type Step = (String, Operation, Option<u8>);
+// Labels only need copying when a line break falls inside one
+pub type Lens<'a> = (Cow<'a, str>, u8);
+pub type Step<'a> = (Cow<'a, str>, Operation, Option<u8>);
 
-#[derive(Debug, Eq, PartialEq)]
-enum Operation {
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Operation {
     Equals,
     Dash,
 }
 
+// Byte offsets into the initialization sequence of the character that made a step invalid.
+#[derive(Debug, Eq, PartialEq)]
+pub enum StepError {
+    EmptyLabel(usize),
+    InvalidOperation(usize),
+    MissingFocalLength(usize),
+    InvalidFocalLength(usize),
+}
+
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StepError::EmptyLabel(offset) => write!(f, "empty label at byte {}", offset),
+            StepError::InvalidOperation(offset) => {
+                write!(f, "invalid operation at byte {}", offset)
+            }
+            StepError::MissingFocalLength(offset) => {
+                write!(f, "missing focal length at byte {}", offset)
+            }
+            StepError::InvalidFocalLength(offset) => {
+                write!(f, "invalid focal length at byte {}", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StepError {}
+
+// Line breaks are ignored wherever they appear in the sequence, both when hashing and parsing.
+fn is_line_break(c: char) -> bool {
+    c == '\n' || c == '\r'
+}
+
+fn strip_line_breaks(s: &str) -> Cow<'_, str> {
+    if s.contains(is_line_break) {
+        Cow::Owned(s.replace(is_line_break, ""))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
 fn hash_u8(mut acc: Wrapping<u8>, c: u8) -> Wrapping<u8> {
     acc += c;
     acc *= 17;
@@ -18,94 +63,248 @@ fn hash_u8(mut acc: Wrapping<u8>, c: u8) -> Wrapping<u8> {
     acc
 }
 
-fn hash_str(s: &str) -> u8 {
-    s.bytes().fold(Wrapping(0u8), hash_u8).0
+pub fn hash_str(s: &str) -> u8 {
+    s.bytes()
+        .filter(|&c| !is_line_break(c as char))
+        .fold(Wrapping(0u8), hash_u8)
+        .0
 }
 
-fn parse_steps(steps: &[String]) -> Vec<Step> {
-    steps
-        .iter()
-        .map(|step| {
-            if let Some(step) = step.strip_suffix('-') {
-                (step.to_string(), Operation::Dash, None)
-            } else {
-                let mut split = step.split('=');
-                let label = split.next().expect("label should always exist").to_string();
-                let focal_length = split
-                    .next()
-                    .expect("equals op should always have focal length")
-                    .parse::<u8>()
-                    .unwrap();
-
-                (label, Operation::Equals, Some(focal_length))
-            }
-        })
-        .collect()
+// Splits the initialization sequence on commas without copying, yielding each step along with
+// its byte offset. Newlines around a step are ignored so wrapped sequences split cleanly.
+pub struct Steps<'a> {
+    input: &'a str,
+    offset: usize,
 }
 
-fn generate_boxes(steps: Vec<Step>) -> HashMap<u8, Vec<Lens>> {
-    let mut box_map: HashMap<u8, Vec<Lens>> = HashMap::new();
+impl<'a> Iterator for Steps<'a> {
+    type Item = (usize, &'a str);
 
-    for (label, operation, focal_length) in steps {
-        let box_index = hash_str(&label);
-        let box_lenses = box_map.entry(box_index).or_default();
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset > self.input.len() {
+            return None;
+        }
 
-        if operation == Operation::Dash {
-            if let Some(index) = box_lenses
-                .iter()
-                .position(|(lens_label, _)| *lens_label == label)
-            {
+        let rest = &self.input[self.offset..];
+        let end = rest.find(',').unwrap_or(rest.len());
+        let raw = &rest[..end];
+        let step = raw.trim_start_matches(is_line_break);
+        let offset = self.offset + raw.len() - step.len();
+        let step = step.trim_end_matches(is_line_break);
+
+        self.offset += end + 1;
+
+        if step.is_empty() && self.offset > self.input.len() {
+            None
+        } else {
+            Some((offset, step))
+        }
+    }
+}
+
+pub fn split_steps(input: &str) -> Steps<'_> {
+    Steps { input, offset: 0 }
+}
+
+pub fn parse_step(offset: usize, step: &str) -> Result<Step<'_>, StepError> {
+    let operation_index = step
+        .find(|c: char| !c.is_ascii_alphabetic() && !is_line_break(c))
+        .ok_or(StepError::InvalidOperation(offset + step.len()))?;
+    let (label, rest) = step.split_at(operation_index);
+    let label = strip_line_breaks(label);
+
+    if label.is_empty() {
+        return Err(StepError::EmptyLabel(offset));
+    }
+
+    let mut rest = rest.chars();
+    let operation = rest.next().expect("step should contain an operation");
+    let operation_offset = offset + operation_index;
+    let focal_length_offset = operation_offset + operation.len_utf8();
+
+    match (operation, strip_line_breaks(rest.as_str()).as_ref()) {
+        ('-', "") => Ok((label, Operation::Dash, None)),
+        ('-', _) => Err(StepError::InvalidOperation(focal_length_offset)),
+        ('=', "") => Err(StepError::MissingFocalLength(focal_length_offset)),
+        ('=', focal_length) => {
+            let focal_length = focal_length
+                .parse::<u8>()
+                .map_err(|_| StepError::InvalidFocalLength(focal_length_offset))?;
+
+            Ok((label, Operation::Equals, Some(focal_length)))
+        }
+        _ => Err(StepError::InvalidOperation(operation_offset)),
+    }
+}
+
+pub fn parse_steps(input: &str) -> impl Iterator<Item = Result<Step<'_>, StepError>> {
+    split_steps(input).map(|(offset, step)| parse_step(offset, step))
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LensLibrary<'a> {
+    boxes: [Vec<Lens<'a>>; 256],
+}
+
+impl<'a> LensLibrary<'a> {
+    pub fn new() -> Self {
+        Self {
+            boxes: std::array::from_fn(|_| Vec::new()),
+        }
+    }
+
+    pub fn apply(&mut self, (label, operation, focal_length): &Step<'a>) {
+        let box_lenses = &mut self.boxes[hash_str(label) as usize];
+        let position = box_lenses
+            .iter()
+            .position(|(lens_label, _)| lens_label == label);
+
+        match (operation, position) {
+            (Operation::Dash, Some(index)) => {
                 box_lenses.remove(index);
             }
-        } else if operation == Operation::Equals {
-            let focal_length = focal_length.expect("equals op should always have focal length");
-
-            if let Some((_, lens_focal_length)) = box_lenses
-                .iter_mut()
-                .find(|(lens_label, _)| *lens_label == label)
-            {
-                *lens_focal_length = focal_length;
-            } else {
-                box_lenses.push((label, focal_length));
+            (Operation::Dash, None) => {}
+            (Operation::Equals, position) => {
+                let focal_length = focal_length.expect("equals op should always have focal length");
+
+                match position {
+                    Some(index) => box_lenses[index].1 = focal_length,
+                    None => box_lenses.push((label.clone(), focal_length)),
+                }
             }
         }
     }
 
-    box_map
+    pub fn focusing_power(&self) -> Result<u64, Error> {
+        let powers = self
+            .boxes
+            .iter()
+            .enumerate()
+            .flat_map(|(box_index, lenses)| {
+                lenses
+                    .iter()
+                    .enumerate()
+                    .map(move |(lens_index, (_, focal_length))| {
+                        checked_product([
+                            box_index as u64 + 1,
+                            lens_index as u64 + 1,
+                            *focal_length as u64,
+                        ])
+                    })
+            })
+            .collect::<Result<Vec<u64>, Error>>()?;
+
+        checked_sum(powers)
+    }
 }
 
-fn calculate_focusing_power(box_map: HashMap<u8, Vec<Lens>>) -> usize {
-    box_map
-        .into_iter()
-        .map(|(box_index, lenses)| {
-            lenses
-                .into_iter()
-                .enumerate()
-                .map(|(lens_index, (_, focal_length))| {
-                    (box_index as usize + 1) * (lens_index + 1) * focal_length as usize
-                })
-                .sum::<usize>()
+impl Default for LensLibrary<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Display for LensLibrary<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (box_index, lenses) in self.boxes.iter().enumerate() {
+            if lenses.is_empty() {
+                continue;
+            }
+
+            write!(f, "Box {}:", box_index)?;
+            for (label, focal_length) in lenses {
+                write!(f, " [{} {}]", label, focal_length)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+// The state of the library after a step, printed the way the puzzle text shows it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Snapshot<'a> {
+    pub step: Step<'a>,
+    pub library: LensLibrary<'a>,
+}
+
+impl fmt::Display for Snapshot<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (label, operation, focal_length) = &self.step;
+
+        match (operation, focal_length) {
+            (Operation::Equals, Some(focal_length)) => {
+                writeln!(f, "After \"{}={}\":", label, focal_length)?
+            }
+            _ => writeln!(f, "After \"{}-\":", label)?,
+        }
+        writeln!(f, "{}", self.library)
+    }
+}
+
+// Returns each step along with the state of the library after it is applied, in order.
+pub fn generate_snapshots<'a>(
+    steps: impl Iterator<Item = Result<Step<'a>, StepError>>,
+) -> Result<Vec<Snapshot<'a>>, StepError> {
+    let mut library = LensLibrary::new();
+
+    steps
+        .map(|step| {
+            let step = step?;
+            library.apply(&step);
+
+            Ok(Snapshot {
+                step,
+                library: library.clone(),
+            })
         })
-        .sum()
+        .collect()
+}
+
+pub fn generate_library<'a>(
+    steps: impl Iterator<Item = Result<Step<'a>, StepError>>,
+) -> Result<LensLibrary<'a>, StepError> {
+    let mut library = LensLibrary::new();
+
+    for step in steps {
+        library.apply(&step?);
+    }
+
+    Ok(library)
 }
 
 #[aoc_generator(day15)]
-fn parse_input(input: &str) -> Vec<String> {
-    input.split(',').map(|s| s.to_string()).collect()
+fn parse_input(input: &str) -> String {
+    input.to_string()
 }
 
 #[aoc(day15, part1)]
-fn part1(steps: &[String]) -> usize {
-    steps.iter().map(|step| hash_str(step) as usize).sum()
+fn part1(input: &str) -> Result<u64, Error> {
+    checked_sum(split_steps(input).map(|(_, step)| hash_str(step) as u64))
 }
 
 #[aoc(day15, part2)]
-fn part2(steps: &[String]) -> usize {
-    let steps = parse_steps(steps);
+fn part2(input: &str) -> Result<u64, Box<dyn std::error::Error>> {
+    Ok(generate_library(parse_steps(input))?.focusing_power()?)
+}
+
+pub struct Day15;
 
-    let box_map = generate_boxes(steps);
+impl Solution for Day15 {
+    type Input = String;
 
-    calculate_focusing_power(box_map)
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input))
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
 }
 
 #[cfg(test)]
@@ -121,19 +320,109 @@ mod tests {
 
     #[test]
     fn test2() {
-        let steps = parse_input(INPUT);
+        let input = parse_input(INPUT);
 
-        let sum: u32 = steps.into_iter().map(|step| hash_str(&step) as u32).sum();
+        let sum: u32 = split_steps(&input)
+            .map(|(_, step)| hash_str(step) as u32)
+            .sum();
 
         assert_eq!(sum, 1320)
     }
 
     #[test]
     fn test3() {
-        let steps = parse_input(INPUT);
-        let steps = parse_steps(&steps);
-        let box_map = generate_boxes(steps);
+        let input = parse_input(INPUT);
+        let library = generate_library(parse_steps(&input)).unwrap();
+
+        assert_eq!(library.focusing_power(), Ok(145));
+    }
+
+    #[test]
+    fn test4() {
+        let input = parse_input(INPUT);
+        let snapshots = generate_snapshots(parse_steps(&input)).unwrap();
+
+        assert_eq!(snapshots.len(), 11);
+        assert_eq!(snapshots[0].library.to_string(), "Box 0: [rn 1]\n");
+        assert_eq!(snapshots[1].library.to_string(), "Box 0: [rn 1]\n");
+        assert_eq!(
+            snapshots[2].library.to_string(),
+            "Box 0: [rn 1]\nBox 1: [qp 3]\n"
+        );
+        assert_eq!(
+            snapshots[3].library.to_string(),
+            "Box 0: [rn 1] [cm 2]\nBox 1: [qp 3]\n"
+        );
+        assert_eq!(snapshots[4].library.to_string(), "Box 0: [rn 1] [cm 2]\n");
+        assert_eq!(
+            snapshots[10].library.to_string(),
+            "Box 0: [rn 1] [cm 2]\nBox 3: [ot 7] [ab 5] [pc 6]\n"
+        );
+        assert_eq!(
+            snapshots[..3]
+                .iter()
+                .map(|snapshot| snapshot.to_string())
+                .collect::<String>(),
+            concat!(
+                "After \"rn=1\":\n",
+                "Box 0: [rn 1]\n",
+                "\n",
+                "After \"cm-\":\n",
+                "Box 0: [rn 1]\n",
+                "\n",
+                "After \"qp=3\":\n",
+                "Box 0: [rn 1]\n",
+                "Box 1: [qp 3]\n",
+                "\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test5() {
+        let steps: Vec<(usize, &str)> = split_steps("rn=1,\ncm-,qp=3\n").collect();
+
+        assert_eq!(steps, vec![(0, "rn=1"), (6, "cm-"), (10, "qp=3")]);
+    }
+
+    #[test]
+    fn test6() {
+        let errors: Vec<StepError> = parse_steps("=1,rn,rn=,rn=x,rn-1,rn*3")
+            .filter_map(|step| step.err())
+            .collect();
+
+        assert_eq!(
+            errors,
+            vec![
+                StepError::EmptyLabel(0),
+                StepError::InvalidOperation(5),
+                StepError::MissingFocalLength(9),
+                StepError::InvalidFocalLength(13),
+                StepError::InvalidOperation(18),
+                StepError::InvalidOperation(22),
+            ]
+        );
+    }
+
+    #[test]
+    fn test7() {
+        let steps: Vec<Step> = parse_steps("r\nn=1,c\nm-")
+            .map(|step| step.unwrap())
+            .collect();
 
-        assert_eq!(calculate_focusing_power(box_map), 145);
+        assert_eq!(hash_str("r\nn"), hash_str("rn"));
+        assert_eq!(
+            steps,
+            vec![
+                (Cow::from("rn"), Operation::Equals, Some(1)),
+                (Cow::from("cm"), Operation::Dash, None)
+            ]
+        );
+        assert_eq!(part1("r\nn=1"), part1("rn=1"));
+        assert_eq!(part2("r\nn=1").unwrap(), 1);
+        assert_eq!(
+            part2("rn=1,qp=").unwrap_err().to_string(),
+            "missing focal length at byte 8"
+        );
     }
 }
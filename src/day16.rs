@@ -1,8 +1,11 @@
+use crate::error::Error;
+use crate::grid::parse_grid;
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
-use grid_2d::{Coord, Grid, Size};
+use grid_2d::{Coord, Grid};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-enum Direction {
+pub enum Direction {
     West,
     North,
     East,
@@ -18,21 +21,23 @@ enum TileType {
     VerticalSplitter,
 }
 
-impl From<char> for TileType {
-    fn from(c: char) -> Self {
+impl TryFrom<char> for TileType {
+    type Error = Error;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
         match c {
-            '.' => TileType::Empty,
-            '/' => TileType::AscendingMirror,
-            '\\' => TileType::DescendingMirror,
-            '-' => TileType::HorizontalSplitter,
-            '|' => TileType::VerticalSplitter,
-            _ => panic!(),
+            '.' => Ok(TileType::Empty),
+            '/' => Ok(TileType::AscendingMirror),
+            '\\' => Ok(TileType::DescendingMirror),
+            '-' => Ok(TileType::HorizontalSplitter),
+            '|' => Ok(TileType::VerticalSplitter),
+            _ => Err(Error::Parse(format!("unexpected tile {:?}", c))),
         }
     }
 }
 
 #[derive(Copy, Clone, Debug)]
-struct Tile {
+pub struct Tile {
     tile_type: TileType,
     visited_west: bool,
     visited_north: bool,
@@ -41,24 +46,26 @@ struct Tile {
 }
 
 impl Tile {
-    fn visited(&self) -> bool {
+    pub fn visited(&self) -> bool {
         self.visited_west || self.visited_north || self.visited_east || self.visited_south
     }
 }
 
-impl From<char> for Tile {
-    fn from(c: char) -> Self {
-        Tile {
-            tile_type: TileType::from(c),
+impl TryFrom<char> for Tile {
+    type Error = Error;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        Ok(Tile {
+            tile_type: TileType::try_from(c)?,
             visited_west: false,
             visited_north: false,
             visited_east: false,
             visited_south: false,
-        }
+        })
     }
 }
 
-fn track_beam(grid: &mut Grid<Tile>, location: Coord, came_from: Direction) {
+pub fn track_beam(grid: &mut Grid<Tile>, location: Coord, came_from: Direction) {
     if let Some(tile) = grid.get_mut(location) {
         match came_from {
             Direction::West => {
@@ -253,7 +260,7 @@ fn track_beam(grid: &mut Grid<Tile>, location: Coord, came_from: Direction) {
     }
 }
 
-fn count_energized_tiles(grid: &Grid<Tile>) -> usize {
+pub fn count_energized_tiles(grid: &Grid<Tile>) -> usize {
     grid.iter().filter(|tile| tile.visited()).count()
 }
 
@@ -265,16 +272,8 @@ fn test_beam_origin(grid: &Grid<Tile>, location: Coord, source_direction: Direct
 }
 
 #[aoc_generator(day16)]
-fn parse_input(input: &str) -> Grid<Tile> {
-    let width = input.chars().position(|c| c == '\n').unwrap();
-    let height = input.chars().step_by(width + 1).count();
-
-    Grid::new_iterator(
-        Size::new(width as u32, height as u32),
-        input
-            .chars()
-            .filter_map(|c| if c == '\n' { None } else { Some(Tile::from(c)) }),
-    )
+fn parse_input(input: &str) -> Result<Grid<Tile>, Error> {
+    parse_grid(input, |c| Tile::try_from(c).ok())
 }
 
 fn clone<T>(grid: &Grid<T>) -> Grid<T>
@@ -314,6 +313,24 @@ fn part2(grid: &Grid<Tile>) -> usize {
         .unwrap()
 }
 
+pub struct Day16;
+
+impl Solution for Day16 {
+    type Input = Grid<Tile>;
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input).into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input).into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -333,7 +350,7 @@ mod tests {
 
     #[test]
     fn test1() {
-        let mut grid = parse_input(INPUT);
+        let mut grid = parse_input(INPUT).unwrap();
         track_beam(&mut grid, Coord::new(0, 0), Direction::West);
 
         assert_eq!(count_energized_tiles(&grid), 46);
@@ -1,5 +1,8 @@
+use crate::error::Error;
+use crate::grid::parse_grid;
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
-use grid_2d::{Coord, Grid, Size};
+use grid_2d::{Coord, Grid};
 use num::abs;
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
@@ -16,11 +19,11 @@ struct HeapState {
     location: Coord,
     came_from: Coord,
     straight_line_count: u32,
-    cost: u32,
+    cost: u64,
 }
 
 impl HeapState {
-    fn new(location: Coord, came_from: Coord, straight_line_count: u32, cost: u32) -> Self {
+    fn new(location: Coord, came_from: Coord, straight_line_count: u32, cost: u64) -> Self {
         HeapState {
             location,
             came_from,
@@ -100,24 +103,24 @@ fn get_potential_neighbours(
     neighbours
 }
 
-fn heuristic(location: Coord, destination: Coord) -> u32 {
-    abs(destination.x - location.x) as u32 + abs(destination.y - location.y) as u32
+fn heuristic(location: Coord, destination: Coord) -> u64 {
+    abs(destination.x - location.x) as u64 + abs(destination.y - location.y) as u64
 }
 
-fn a_star(
+pub fn a_star(
     cost_map: &Grid<u32>,
     start: Coord,
     destination: Coord,
     min_straight: u32,
     max_straight: u32,
-) -> u32 {
+) -> Result<u64, Error> {
     let mut unvisited: BinaryHeap<HeapState> = BinaryHeap::new();
     unvisited.push(HeapState::new(start, start, 0, 0));
 
-    let mut f_scores: HashMap<(Coord, Coord, u32), u32> = HashMap::new();
+    let mut f_scores: HashMap<(Coord, Coord, u32), u64> = HashMap::new();
     f_scores.insert((start, start, 0), 0);
 
-    let mut g_scores: HashMap<(Coord, Coord, u32), u32> = HashMap::new();
+    let mut g_scores: HashMap<(Coord, Coord, u32), u64> = HashMap::new();
     g_scores.insert((start, start, 0), 0);
 
     let mut came_from: HashMap<Coord, Coord> = HashMap::new();
@@ -134,8 +137,9 @@ fn a_star(
             .get(&(node.location, node.came_from, node.straight_line_count))
             .unwrap();
 
-        if node.location == destination {
-            break current_g_score;
+        // An ultra crucible needs to have moved at least min_straight blocks before it can stop.
+        if node.location == destination && node.straight_line_count >= min_straight {
+            break Ok(current_g_score);
         }
 
         let can_continue_straight = if node.location == start {
@@ -166,12 +170,16 @@ fn a_star(
             };
             let neighbour_node = (neighbour_location, node.location, straight_line_count);
 
-            let tentative_g_score = current_g_score + cost_map.get(neighbour_location).unwrap();
-            if tentative_g_score < *g_scores.entry(neighbour_node).or_insert(u32::MAX) {
+            let tentative_g_score = current_g_score
+                .checked_add(*cost_map.get(neighbour_location).unwrap() as u64)
+                .ok_or(Error::Overflow)?;
+            if tentative_g_score < *g_scores.entry(neighbour_node).or_insert(u64::MAX) {
                 g_scores.insert(neighbour_node, tentative_g_score);
                 f_scores.insert(
                     neighbour_node,
-                    tentative_g_score + heuristic(node.location, destination),
+                    tentative_g_score
+                        .checked_add(heuristic(node.location, destination))
+                        .ok_or(Error::Overflow)?,
                 );
                 came_from.insert(neighbour_location, node.location);
 
@@ -187,32 +195,42 @@ fn a_star(
 }
 
 #[aoc_generator(day17)]
-fn parse_input(input: &str) -> Grid<u32> {
-    let width = input.chars().position(|c| c == '\n').unwrap();
-    let height = input.chars().step_by(width + 1).count();
-
-    Grid::new_iterator(
-        Size::new(width as u32, height as u32),
-        input
-            .chars()
-            .filter_map(|c| if c == '\n' { None } else { c.to_digit(10) }),
-    )
+fn parse_input(input: &str) -> Result<Grid<u32>, Error> {
+    parse_grid(input, |c| c.to_digit(10))
 }
 
 #[aoc(day17, part1)]
-fn part1(grid: &Grid<u32>) -> u32 {
+fn part1(grid: &Grid<u32>) -> Result<u64, Error> {
     let start = Coord::new(0, 0);
     let destination = Coord::new(grid.width() as i32 - 1, grid.height() as i32 - 1);
     a_star(grid, start, destination, 0, 3)
 }
 
 #[aoc(day17, part2)]
-fn part2(grid: &Grid<u32>) -> u32 {
+fn part2(grid: &Grid<u32>) -> Result<u64, Error> {
     let start = Coord::new(0, 0);
     let destination = Coord::new(grid.width() as i32 - 1, grid.height() as i32 - 1);
     a_star(grid, start, destination, 4, 10)
 }
 
+pub struct Day17;
+
+impl Solution for Day17 {
+    type Input = Grid<u32>;
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -235,12 +253,12 @@ mod tests {
 
     #[test]
     fn test1() {
-        let grid = parse_input(INPUT);
+        let grid = parse_input(INPUT).unwrap();
         let start = Coord::new(0, 0);
         let destination = Coord::new(grid.width() as i32 - 1, grid.height() as i32 - 1);
 
         let heat_loss = a_star(&grid, start, destination, 1, 3);
 
-        assert_eq!(heat_loss, 102);
+        assert_eq!(heat_loss, Ok(102));
     }
 }
@@ -1,118 +1,144 @@
+use crate::error::{checked_sum, parse_all, Error};
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
-use nom::bytes::complete::tag;
-use nom::character::complete::{anychar, hex_digit1, newline, space1};
+use nom::bytes::complete::{tag, take_while_m_n};
+use nom::character::complete::{anychar, newline, space1};
+use nom::combinator::map_res;
 use nom::multi::separated_list1;
-use nom::sequence::{delimited, preceded, separated_pair};
+use nom::sequence::{delimited, pair, preceded, separated_pair};
 use nom::IResult;
-use num::abs;
 
-type Step = (Direction, u64);
+pub type Step = (Direction, u64);
 
 #[derive(Copy, Clone, Debug)]
-enum Direction {
+pub enum Direction {
     Up,
     Down,
     Left,
     Right,
 }
 
-impl From<char> for Direction {
-    fn from(c: char) -> Direction {
+impl TryFrom<char> for Direction {
+    type Error = Error;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
         match c {
-            'U' => Direction::Up,
-            'D' => Direction::Down,
-            'L' => Direction::Left,
-            'R' => Direction::Right,
-            '3' => Direction::Up,
-            '1' => Direction::Down,
-            '2' => Direction::Left,
-            '0' => Direction::Right,
-            _ => panic!(),
+            'U' => Ok(Direction::Up),
+            'D' => Ok(Direction::Down),
+            'L' => Ok(Direction::Left),
+            'R' => Ok(Direction::Right),
+            '3' => Ok(Direction::Up),
+            '1' => Ok(Direction::Down),
+            '2' => Ok(Direction::Left),
+            '0' => Ok(Direction::Right),
+            _ => Err(Error::Parse(format!("unexpected direction {:?}", c))),
         }
     }
 }
 
-fn shoelace_formula(vertices: &[(i64, i64)]) -> u64 {
-    let first_part: i64 = vertices
-        .iter()
-        .map(|(x, _)| x)
-        .zip(vertices.iter().skip(1).map(|(_, y)| y))
-        .map(|(x, y)| x * y)
-        .sum();
-    let second_part: i64 = vertices
-        .iter()
-        .skip(1)
-        .map(|(x, _)| x)
-        .zip(vertices.iter().map(|(_, y)| y))
-        .map(|(x, y)| x * y)
-        .sum();
-
-    abs(first_part - second_part) as u64 / 2
+pub fn shoelace_formula(vertices: &[(i64, i64)]) -> Result<u64, Error> {
+    let twice_area = checked_sum(
+        vertices
+            .iter()
+            .zip(vertices.iter().skip(1))
+            .map(|(&(x1, y1), &(x2, y2))| x1 as i128 * y2 as i128 - x2 as i128 * y1 as i128),
+    )?;
+
+    u64::try_from(twice_area.unsigned_abs() / 2).map_err(|_| Error::Overflow)
 }
 
-fn loop_area(dig_plans: &[Step]) -> u64 {
+pub fn loop_area(dig_plans: &[Step]) -> Result<u64, Error> {
     let mut x: i64 = 0;
     let mut y: i64 = 0;
     let mut vertices = vec![(x, y)];
 
-    let mut loop_length = 0;
+    let mut loop_length: u64 = 0;
 
     for (direction, distance) in dig_plans {
-        let distance = *distance as i64;
-        match direction {
-            Direction::Up => y += distance,
-            Direction::Down => y -= distance,
-            Direction::Left => x -= distance,
-            Direction::Right => x += distance,
+        let signed_distance = i64::try_from(*distance).map_err(|_| Error::Overflow)?;
+        let moved = match direction {
+            Direction::Up => y.checked_add(signed_distance).map(|moved| y = moved),
+            Direction::Down => y.checked_sub(signed_distance).map(|moved| y = moved),
+            Direction::Left => x.checked_sub(signed_distance).map(|moved| x = moved),
+            Direction::Right => x.checked_add(signed_distance).map(|moved| x = moved),
         };
+        moved.ok_or(Error::Overflow)?;
 
         vertices.push((x, y));
-        loop_length += distance;
+        loop_length = loop_length.checked_add(*distance).ok_or(Error::Overflow)?;
     }
 
     // Add loop_length / 2 + 1 to account for the shoelace vertices tracing the inside of the blocks
     // rather than the other edge
-    shoelace_formula(&vertices) + (loop_length / 2) as u64 + 1
+    checked_sum([shoelace_formula(&vertices)?, loop_length / 2, 1])
 }
 
 fn parse_line(input: &str) -> IResult<&str, (Step, Step)> {
     // R 4 (#9505a2)
-    let (input, (direction, distance)) =
-        separated_pair(anychar, space1, nom::character::complete::u64)(input)?;
-    let (input, hex_value) = preceded(space1, delimited(tag("(#"), hex_digit1, tag(")")))(input)?;
-
-    let hex_distance = u64::from_str_radix(&hex_value[0..5], 16).unwrap();
-    let hex_direction = Direction::from(hex_value.chars().nth(5).unwrap());
+    let (input, (direction, distance)) = separated_pair(
+        map_res(anychar, Direction::try_from),
+        space1,
+        nom::character::complete::u64,
+    )(input)?;
+    let (input, (hex_distance, hex_direction)) = preceded(
+        space1,
+        delimited(
+            tag("(#"),
+            pair(
+                map_res(
+                    take_while_m_n(5, 5, |c: char| c.is_ascii_hexdigit()),
+                    |hex| u64::from_str_radix(hex, 16),
+                ),
+                map_res(anychar, Direction::try_from),
+            ),
+            tag(")"),
+        ),
+    )(input)?;
 
     Ok((
         input,
-        (
-            (Direction::from(direction), distance),
-            (hex_direction, hex_distance),
-        ),
+        ((direction, distance), (hex_direction, hex_distance)),
     ))
 }
 
 #[aoc_generator(day18)]
-fn parse_input(input: &str) -> (Vec<Step>, Vec<Step>) {
-    let (_, lines) = separated_list1(newline, parse_line)(input).unwrap();
+fn parse_input(input: &str) -> Result<(Vec<Step>, Vec<Step>), Error> {
+    let lines = parse_all(separated_list1(newline, parse_line), input)?;
 
-    (
+    Ok((
         lines.iter().map(|(first, _)| first).copied().collect(),
         lines.iter().map(|(_, second)| second).copied().collect(),
-    )
+    ))
 }
 
 #[aoc(day18, part1)]
-fn part1((dig_plans, _): &(Vec<Step>, Vec<Step>)) -> u64 {
+fn part1((dig_plans, _): &(Vec<Step>, Vec<Step>)) -> Result<u64, Error> {
     loop_area(dig_plans)
 }
 
 #[aoc(day18, part2)]
-fn part2((_, dig_plans): &(Vec<Step>, Vec<Step>)) -> u64 {
+fn part2((_, dig_plans): &(Vec<Step>, Vec<Step>)) -> Result<u64, Error> {
     loop_area(dig_plans)
 }
 
+pub struct Day18;
+
+impl Solution for Day18 {
+    type Input = (Vec<Step>, Vec<Step>);
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -136,15 +162,15 @@ mod tests {
 
     #[test]
     fn test1() {
-        let (dig_plans, _) = parse_input(INPUT);
+        let (dig_plans, _) = parse_input(INPUT).unwrap();
 
-        assert_eq!(loop_area(&dig_plans), 62);
+        assert_eq!(loop_area(&dig_plans), Ok(62));
     }
 
     #[test]
     fn test2() {
-        let (_, dig_plans) = parse_input(INPUT);
+        let (_, dig_plans) = parse_input(INPUT).unwrap();
 
-        assert_eq!(loop_area(&dig_plans), 952408144115);
+        assert_eq!(loop_area(&dig_plans), Ok(952408144115));
     }
 }
@@ -1,75 +1,83 @@
+use crate::error::{checked_product, checked_sum, parse_all, Error};
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take_till};
 use nom::character::complete::{alpha1, anychar, newline};
+use nom::combinator::map_res;
 use nom::multi::separated_list1;
 use nom::sequence::{delimited, preceded};
 use nom::IResult;
 use std::collections::HashMap;
 use std::ops::Range;
 
-type Workflow = (String, Vec<Rule>);
+pub type Workflow = (String, Vec<Rule>);
+pub type Workflows = HashMap<String, Vec<Rule>>;
 
-const ACCEPT: &str = "A";
-const REJECT: &str = "R";
+pub const ACCEPT: &str = "A";
+pub const REJECT: &str = "R";
 
 #[derive(Debug, Eq, PartialEq)]
-enum Evaluation {
+pub enum Evaluation {
     Accept,
     Reject,
 }
 
 #[derive(Debug)]
-enum Attribute {
+pub enum Attribute {
     X,
     M,
     A,
     S,
 }
 
-impl From<char> for Attribute {
-    fn from(c: char) -> Self {
+impl TryFrom<char> for Attribute {
+    type Error = Error;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
         match c {
-            'x' => Attribute::X,
-            'm' => Attribute::M,
-            'a' => Attribute::A,
-            's' => Attribute::S,
-            _ => panic!(),
+            'x' => Ok(Attribute::X),
+            'm' => Ok(Attribute::M),
+            'a' => Ok(Attribute::A),
+            's' => Ok(Attribute::S),
+            _ => Err(Error::Parse(format!("unexpected attribute {:?}", c))),
         }
     }
 }
 
 #[derive(Debug, Eq, PartialEq)]
-enum Operator {
+pub enum Operator {
     Less,
     Greater,
 }
 
-impl From<char> for Operator {
-    fn from(c: char) -> Self {
+impl TryFrom<char> for Operator {
+    type Error = Error;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
         match c {
-            '<' => Operator::Less,
-            '>' => Operator::Greater,
-            _ => panic!(),
+            '<' => Ok(Operator::Less),
+            '>' => Ok(Operator::Greater),
+            _ => Err(Error::Parse(format!("unexpected operator {:?}", c))),
         }
     }
 }
 
 #[derive(Debug)]
-struct Rule {
+pub struct Rule {
     operation: Option<(Attribute, Operator, u64)>,
     destination: String,
 }
 
 impl Rule {
-    fn new(destination: String) -> Self {
+    pub fn new(destination: String) -> Self {
         Rule {
             operation: None,
             destination,
         }
     }
 
-    fn new_with_operation(
+    pub fn new_with_operation(
         destination: String,
         attribute: Attribute,
         operator: Operator,
@@ -83,19 +91,19 @@ impl Rule {
 }
 
 #[derive(Debug)]
-struct Part {
-    x: u64,
-    m: u64,
-    a: u64,
-    s: u64,
+pub struct Part {
+    pub x: u64,
+    pub m: u64,
+    pub a: u64,
+    pub s: u64,
 }
 
 impl Part {
-    fn new(x: u64, m: u64, a: u64, s: u64) -> Self {
+    pub fn new(x: u64, m: u64, a: u64, s: u64) -> Self {
         Part { x, m, a, s }
     }
 
-    fn matches_rule(&self, rule: &Rule) -> bool {
+    pub fn matches_rule(&self, rule: &Rule) -> bool {
         if let Some((attribute, operator, value)) = &rule.operation {
             let attribute = match attribute {
                 Attribute::X => self.x,
@@ -113,12 +121,12 @@ impl Part {
         }
     }
 
-    fn rating(&self) -> u64 {
-        self.x + self.m + self.a + self.s
+    pub fn rating(&self) -> Result<u64, Error> {
+        checked_sum([self.x, self.m, self.a, self.s])
     }
 }
 
-fn apply_workflows(part: &Part, workflows: &HashMap<String, Vec<Rule>>, start: &str) -> Evaluation {
+pub fn apply_workflows(part: &Part, workflows: &Workflows, start: &str) -> Evaluation {
     let mut workflow = start.to_string();
 
     loop {
@@ -140,20 +148,15 @@ fn apply_workflows(part: &Part, workflows: &HashMap<String, Vec<Rule>>, start: &
 }
 
 fn parse_rule_with_operation(input: &str) -> IResult<&str, Rule> {
-    let (input, attribute) = anychar(input)?;
-    let (input, operator) = anychar(input)?;
+    let (input, attribute) = map_res(anychar, Attribute::try_from)(input)?;
+    let (input, operator) = map_res(anychar, Operator::try_from)(input)?;
     let (input, value) = nom::character::complete::u64(input)?;
     let (input, _) = tag(":")(input)?;
     let (input, destination) = alpha1(input)?;
 
     Ok((
         input,
-        Rule::new_with_operation(
-            destination.to_string(),
-            Attribute::from(attribute),
-            Operator::from(operator),
-            value,
-        ),
+        Rule::new_with_operation(destination.to_string(), attribute, operator, value),
     ))
 }
 
@@ -190,33 +193,38 @@ fn parse_part(input: &str) -> IResult<&str, Part> {
 }
 
 #[aoc_generator(day19)]
-fn parse_input(input: &str) -> (HashMap<String, Vec<Rule>>, Vec<Part>) {
-    let (input, workflows) = separated_list1(newline, parse_workflow)(input).unwrap();
-    let (_, parts) = preceded(tag("\n\n"), separated_list1(newline, parse_part))(input).unwrap();
+fn parse_input(input: &str) -> Result<(Workflows, Vec<Part>), Error> {
+    let (input, workflows) = separated_list1(newline, parse_workflow)(input)?;
+    let parts = parse_all(
+        preceded(tag("\n\n"), separated_list1(newline, parse_part)),
+        input,
+    )?;
 
-    let workflows: HashMap<String, Vec<Rule>> = workflows.into_iter().collect();
-    (workflows, parts)
+    let workflows: Workflows = workflows.into_iter().collect();
+    Ok((workflows, parts))
 }
 
 #[aoc(day19, part1)]
-fn part1((workflows, parts): &(HashMap<String, Vec<Rule>>, Vec<Part>)) -> u64 {
-    parts
+fn part1((workflows, parts): &(Workflows, Vec<Part>)) -> Result<u64, Error> {
+    let ratings = parts
         .iter()
         .filter(|part| apply_workflows(part, workflows, "in") == Evaluation::Accept)
         .map(|part| part.rating())
-        .sum()
+        .collect::<Result<Vec<u64>, Error>>()?;
+
+    checked_sum(ratings)
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
-struct PossibleParts {
-    x: Range<u64>,
-    m: Range<u64>,
-    a: Range<u64>,
-    s: Range<u64>,
+pub struct PossibleParts {
+    pub x: Range<u64>,
+    pub m: Range<u64>,
+    pub a: Range<u64>,
+    pub s: Range<u64>,
 }
 
 impl PossibleParts {
-    fn new(min: u64, max: u64) -> Self {
+    pub fn new(min: u64, max: u64) -> Self {
         PossibleParts {
             x: min..max + 1,
             m: min..max + 1,
@@ -225,14 +233,11 @@ impl PossibleParts {
         }
     }
 
-    fn possibilities(&self) -> u64 {
-        (self.x.end - self.x.start)
-            * (self.m.end - self.m.start)
-            * (self.a.end - self.a.start)
-            * (self.s.end - self.s.start)
+    pub fn possibilities(&self) -> Result<u64, Error> {
+        checked_product([&self.x, &self.m, &self.a, &self.s].map(|range| range.end - range.start))
     }
 
-    fn split_by_rule(self, rule: &Rule) -> (Option<Self>, Option<Self>) {
+    pub fn split_by_rule(self, rule: &Rule) -> (Option<Self>, Option<Self>) {
         if let Some((attribute, operator, value)) = &rule.operation {
             let mut matched = self.clone();
             let mut unmatched = self.clone();
@@ -269,8 +274,8 @@ impl PossibleParts {
     }
 }
 
-fn evaluate_parts(
-    workflows: &HashMap<String, Vec<Rule>>,
+pub fn evaluate_parts(
+    workflows: &Workflows,
     workflow: String,
     parts: PossibleParts,
 ) -> HashMap<PossibleParts, String> {
@@ -310,20 +315,43 @@ fn evaluate_parts(
     map
 }
 
-fn calculate_accepted_possibilities(map: &HashMap<PossibleParts, String>) -> u64 {
-    map.iter()
+pub fn calculate_accepted_possibilities(
+    map: &HashMap<PossibleParts, String>,
+) -> Result<u64, Error> {
+    let possibilities = map
+        .iter()
         .filter(|(_, eval)| *eval == ACCEPT)
         .map(|(parts, _)| parts.possibilities())
-        .sum()
+        .collect::<Result<Vec<u64>, Error>>()?;
+
+    checked_sum(possibilities)
 }
 
 #[aoc(day19, part2)]
-fn part2((workflows, _): &(HashMap<String, Vec<Rule>>, Vec<Part>)) -> u64 {
+fn part2((workflows, _): &(Workflows, Vec<Part>)) -> Result<u64, Error> {
     let parts = PossibleParts::new(1, 4000);
     let stuff = evaluate_parts(workflows, "in".to_string(), parts);
     calculate_accepted_possibilities(&stuff)
 }
 
+pub struct Day19;
+
+impl Solution for Day19 {
+    type Input = (Workflows, Vec<Part>);
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -350,12 +378,12 @@ mod tests {
 
     #[test]
     fn test1() {
-        let (workflows, parts) = parse_input(INPUT);
+        let (workflows, parts) = parse_input(INPUT).unwrap();
 
         let sum: u64 = parts
             .iter()
-            .filter(|part| apply_workflows(part, &workflows, &"in") == Evaluation::Accept)
-            .map(|part| part.rating())
+            .filter(|part| apply_workflows(part, &workflows, "in") == Evaluation::Accept)
+            .map(|part| part.rating().unwrap())
             .sum();
 
         assert_eq!(sum, 19114);
@@ -363,12 +391,12 @@ mod tests {
 
     #[test]
     fn test2() {
-        let (workflows, _) = parse_input(INPUT);
+        let (workflows, _) = parse_input(INPUT).unwrap();
 
         let parts = PossibleParts::new(1, 4000);
         let stuff = evaluate_parts(&workflows, "in".to_string(), parts);
         let sum = calculate_accepted_possibilities(&stuff);
 
-        assert_eq!(sum, 167409079868000);
+        assert_eq!(sum, Ok(167409079868000));
     }
 }
@@ -1,3 +1,5 @@
+use crate::error::{parse_all, Error};
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
 use nom::branch::alt;
 use nom::bytes::complete::tag;
@@ -11,7 +13,7 @@ static BROADCASTER: &str = "broadcaster";
 static BUTTON: &str = "button";
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-enum Pulse {
+pub enum Pulse {
     Low,
     High,
 }
@@ -128,13 +130,13 @@ impl Module {
 }
 
 #[derive(Debug)]
-struct ModuleNetwork {
+pub struct ModuleNetwork {
     modules: HashMap<String, Module>,
     pulse_queue: VecDeque<(Pulse, String, String)>,
 }
 
 impl ModuleNetwork {
-    fn new(module_list: &[(String, String, Vec<String>)]) -> Self {
+    pub fn new(module_list: &[(String, String, Vec<String>)]) -> Self {
         let mut modules: HashMap<String, Module> = module_list
             .iter()
             .map(|(module_type, name, outputs)| {
@@ -159,7 +161,7 @@ impl ModuleNetwork {
         }
     }
 
-    fn press_button(&mut self) {
+    pub fn press_button(&mut self) {
         self.modules
             .get_mut(BUTTON)
             .expect("button should be added during initializations")
@@ -177,20 +179,54 @@ impl ModuleNetwork {
         }
     }
 
-    fn press_button_n(&mut self, n: u64) {
+    pub fn press_button_n(&mut self, n: u64) {
         for _ in 0..n {
             self.press_button();
         }
     }
 
-    fn low_pulses_sent(&self) -> u64 {
+    // Graphviz description of the network, drawing each kind of module with its own shape. The
+    // implicit button module is left out.
+    pub fn to_dot(&self) -> String {
+        let mut names: Vec<&String> = self.modules.keys().collect();
+        names.sort();
+
+        let mut dot = "digraph modules {\n".to_string();
+
+        for name in &names {
+            let shape = match self.modules[*name].module {
+                ModuleType::FlipFlop { .. } => "box",
+                ModuleType::Conjunction { .. } => "invhouse",
+                ModuleType::Broadcaster => "doublecircle",
+                ModuleType::Button => continue,
+            };
+
+            dot += &format!("    \"{}\" [shape={}];\n", name, shape);
+        }
+
+        for name in &names {
+            if *name == BUTTON {
+                continue;
+            }
+
+            for output in &self.modules[*name].outputs {
+                dot += &format!("    \"{}\" -> \"{}\";\n", name, output);
+            }
+        }
+
+        dot += "}\n";
+
+        dot
+    }
+
+    pub fn low_pulses_sent(&self) -> u64 {
         self.modules
             .values()
             .map(|module| module.low_pulses_sent)
             .sum()
     }
 
-    fn high_pulses_sent(&self) -> u64 {
+    pub fn high_pulses_sent(&self) -> u64 {
         self.modules
             .values()
             .map(|module| module.high_pulses_sent)
@@ -243,23 +279,44 @@ fn parse_line(input: &str) -> IResult<&str, (String, String, Vec<String>)> {
 }
 
 #[aoc_generator(day20)]
-fn parse_input(input: &str) -> Vec<(String, String, Vec<String>)> {
-    let (_, lines) = separated_list1(newline, parse_line)(input).unwrap();
+fn parse_input(input: &str) -> Result<Vec<(String, String, Vec<String>)>, Error> {
+    let lines = parse_all(separated_list1(newline, parse_line), input)?;
 
-    lines
+    Ok(lines)
 }
 
 #[aoc(day20, part1)]
-fn part1(modules: &[(String, String, Vec<String>)]) -> u64 {
+fn part1(modules: &[(String, String, Vec<String>)]) -> Result<u64, Error> {
     let mut network = ModuleNetwork::new(modules);
     network.press_button_n(1000);
 
-    network.low_pulses_sent() * network.high_pulses_sent()
+    network
+        .low_pulses_sent()
+        .checked_mul(network.high_pulses_sent())
+        .ok_or(Error::Overflow)
 }
 
 #[aoc(day20, part2)]
-fn part2(_modules: &[(String, String, Vec<String>)]) -> u64 {
-    0
+fn part2(_modules: &[(String, String, Vec<String>)]) -> Result<u64, Error> {
+    Err(Error::Unsolved)
+}
+
+pub struct Day20;
+
+impl Solution for Day20 {
+    type Input = Vec<(String, String, Vec<String>)>;
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input)?.into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
 }
 
 #[cfg(test)]
@@ -284,7 +341,7 @@ mod tests {
 
     #[test]
     fn test1() {
-        let modules = parse_input(INPUT1);
+        let modules = parse_input(INPUT1).unwrap();
         let mut network = ModuleNetwork::new(&modules);
         network.press_button_n(1000);
 
@@ -296,7 +353,7 @@ mod tests {
 
     #[test]
     fn test2() {
-        let modules = parse_input(INPUT2);
+        let modules = parse_input(INPUT2).unwrap();
         let mut network = ModuleNetwork::new(&modules);
         network.press_button_n(1000);
 
@@ -305,4 +362,29 @@ mod tests {
             11687500
         );
     }
+
+    #[test]
+    fn test3() {
+        let modules = parse_input(INPUT2).unwrap();
+        let network = ModuleNetwork::new(&modules);
+
+        assert_eq!(
+            network.to_dot(),
+            concat!(
+                "digraph modules {\n",
+                "    \"a\" [shape=box];\n",
+                "    \"b\" [shape=box];\n",
+                "    \"broadcaster\" [shape=doublecircle];\n",
+                "    \"con\" [shape=invhouse];\n",
+                "    \"inv\" [shape=invhouse];\n",
+                "    \"a\" -> \"inv\";\n",
+                "    \"a\" -> \"con\";\n",
+                "    \"b\" -> \"con\";\n",
+                "    \"broadcaster\" -> \"a\";\n",
+                "    \"con\" -> \"output\";\n",
+                "    \"inv\" -> \"b\";\n",
+                "}\n",
+            )
+        );
+    }
 }
@@ -1,21 +1,26 @@
+use crate::error::Error;
+use crate::grid::parse_grid;
+use crate::solution::{ParseResult, PartResult, Solution};
 use aoc_runner_derive::{aoc, aoc_generator};
-use grid_2d::{Coord, Grid, Size};
+use grid_2d::{Coord, Grid};
 use std::collections::{HashMap, HashSet};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-enum Tile {
+pub enum Tile {
     Plot,
     Rock,
     Start,
 }
 
-impl From<char> for Tile {
-    fn from(c: char) -> Tile {
+impl TryFrom<char> for Tile {
+    type Error = Error;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
         match c {
-            '.' => Tile::Plot,
-            '#' => Tile::Rock,
-            'S' => Tile::Start,
-            _ => panic!("unexpected tile"),
+            '.' => Ok(Tile::Plot),
+            '#' => Ok(Tile::Rock),
+            'S' => Ok(Tile::Start),
+            _ => Err(Error::Parse(format!("unexpected tile {:?}", c))),
         }
     }
 }
@@ -29,7 +34,7 @@ fn get_neighbours(location: Coord) -> [Coord; 4] {
     ]
 }
 
-fn calculate_distances_from_start(grid: &Grid<Tile>) -> HashMap<Coord, u64> {
+pub fn calculate_distances_from_start(grid: &Grid<Tile>) -> HashMap<Coord, u64> {
     let (start, _) = grid
         .enumerate()
         .find(|(_, tile)| **tile == Tile::Start)
@@ -59,16 +64,8 @@ fn calculate_distances_from_start(grid: &Grid<Tile>) -> HashMap<Coord, u64> {
 }
 
 #[aoc_generator(day21)]
-fn parse_input(input: &str) -> Grid<Tile> {
-    let width = input.chars().position(|c| c == '\n').unwrap();
-    let height = input.chars().step_by(width + 1).count();
-
-    Grid::new_iterator(
-        Size::new(width as u32, height as u32),
-        input
-            .chars()
-            .filter_map(|c| if c == '\n' { None } else { Some(Tile::from(c)) }),
-    )
+fn parse_input(input: &str) -> Result<Grid<Tile>, Error> {
+    parse_grid(input, |c| Tile::try_from(c).ok())
 }
 
 #[aoc(day21, part1)]
@@ -84,8 +81,26 @@ fn part1(grid: &Grid<Tile>) -> u64 {
 }
 
 #[aoc(day21, part2)]
-fn part2(_grid: &Grid<Tile>) -> u64 {
-    0
+fn part2(_grid: &Grid<Tile>) -> Result<u64, Error> {
+    Err(Error::Unsolved)
+}
+
+pub struct Day21;
+
+impl Solution for Day21 {
+    type Input = Grid<Tile>;
+
+    fn parse(input: &str) -> ParseResult<Self::Input> {
+        Ok(parse_input(input)?)
+    }
+
+    fn part1(input: &Self::Input) -> PartResult {
+        Ok(part1(input).into())
+    }
+
+    fn part2(input: &Self::Input) -> PartResult {
+        Ok(part2(input)?.into())
+    }
 }
 
 #[cfg(test)]
@@ -109,7 +124,7 @@ mod tests {
     #[test]
     fn test1() {
         const STEPS: u64 = 6;
-        let grid = parse_input(INPUT);
+        let grid = parse_input(INPUT).unwrap();
         let distances_from_start = calculate_distances_from_start(&grid);
 
         let count = distances_from_start
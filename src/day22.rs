@@ -1,10 +1,10 @@
+use crate::error::{parse_all, Error};
 use aoc_runner_derive::{aoc, aoc_generator};
 use nom::bytes::complete::tag;
 use nom::character::complete::newline;
 use nom::multi::separated_list1;
 use nom::sequence::{preceded, separated_pair, tuple};
 use nom::IResult;
-use std::cmp::Ordering;
 use std::ops::Range;
 
 #[derive(Debug)]
@@ -61,21 +61,6 @@ impl Block {
     }
 }
 
-impl Ord for Block {
-    fn cmp(&self, other: &Self) -> Ordering {
-        // Reverse comparison to turn BinaryHeap into a min-heap
-        let z = other.z.cmp(&self.z);
-
-        other.cost.cmp(&self.cost)
-    }
-}
-
-impl PartialOrd for HeapState {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
 fn parse_point(input: &str) -> IResult<&str, Point> {
     let (input, coords) = tuple((
         nom::character::complete::u64,
@@ -93,16 +78,14 @@ fn parse_line(input: &str) -> IResult<&str, Block> {
 }
 
 #[aoc_generator(day22)]
-fn parse_input(input: &str) -> Vec<Block> {
-    let (_, blocks) = separated_list1(newline, parse_line)(input).unwrap();
+fn parse_input(input: &str) -> Result<Vec<Block>, Error> {
+    let blocks = parse_all(separated_list1(newline, parse_line), input)?;
 
-    blocks
+    Ok(blocks)
 }
 
 #[aoc(day22, part1)]
-fn part1(blocks: &[Block]) -> u64 {
-    let block_heap = blocks
-
+fn part1(_input: &[Block]) -> u64 {
     0
 }
 
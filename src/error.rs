@@ -0,0 +1,94 @@
+use nom::character::complete::multispace0;
+use nom::combinator::all_consuming;
+use nom::sequence::terminated;
+use nom::IResult;
+use num::traits::{CheckedAdd, CheckedMul};
+use num::{One, Zero};
+use std::fmt;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Error {
+    Overflow,
+    Parse(String),
+    UnknownCard(char),
+    NoDigits { line: usize },
+    ZeroFactor,
+    NoGalaxy { galaxy: usize, count: usize },
+    PastEnd { card: usize, matches: usize },
+    NoCard { card: usize, count: usize },
+    RuleSet(String),
+    NoSolution(String),
+    EmptySequence,
+    NoZeroDifferences,
+    Unsolved,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Overflow => write!(f, "arithmetic overflow"),
+            Error::Parse(message) => write!(f, "cannot parse input: {}", message),
+            Error::UnknownCard(card) => write!(f, "card {:?} isn't in the deck", card),
+            Error::NoDigits { line } => write!(f, "line {} has no digits", line),
+            Error::ZeroFactor => write!(f, "expansion factor must be at least 1"),
+            Error::NoGalaxy { galaxy, count } => {
+                write!(f, "no galaxy {} among {}", galaxy, count)
+            }
+            Error::PastEnd { card, matches } => write!(
+                f,
+                "card {} has {} matches, running past the last card",
+                card, matches
+            ),
+            Error::NoCard { card, count } => write!(f, "no card {} among {}", card, count),
+            Error::RuleSet(message) => write!(f, "invalid rule set: {}", message),
+            Error::NoSolution(message) => write!(f, "no solution: {}", message),
+            Error::EmptySequence => write!(f, "empty sequence"),
+            Error::NoZeroDifferences => {
+                write!(f, "sequence never reaches all-zero differences")
+            }
+            Error::Unsolved => write!(f, "not solved yet"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Points at the line the parser gave up on rather than echoing the rest of the input.
+impl From<nom::Err<nom::error::Error<&str>>> for Error {
+    fn from(error: nom::Err<nom::error::Error<&str>>) -> Self {
+        match error {
+            nom::Err::Incomplete(_) => Error::Parse("unexpected end of input".to_string()),
+            nom::Err::Error(error) | nom::Err::Failure(error) => match error.input.lines().next() {
+                Some(line) => Error::Parse(format!("unexpected {:?}", line)),
+                None => Error::Parse("unexpected end of input".to_string()),
+            },
+        }
+    }
+}
+
+// Runs a parser over the whole input, so anything it stops short of apart from trailing whitespace
+// is an error rather than silently dropped.
+pub fn parse_all<'a, O>(
+    parser: impl FnMut(&'a str) -> IResult<&'a str, O>,
+    input: &'a str,
+) -> Result<O, Error> {
+    let (_, output) = all_consuming(terminated(parser, multispace0))(input)?;
+
+    Ok(output)
+}
+
+pub fn checked_sum<T: CheckedAdd + Zero>(values: impl IntoIterator<Item = T>) -> Result<T, Error> {
+    values
+        .into_iter()
+        .try_fold(T::zero(), |acc, value| acc.checked_add(&value))
+        .ok_or(Error::Overflow)
+}
+
+pub fn checked_product<T: CheckedMul + One>(
+    values: impl IntoIterator<Item = T>,
+) -> Result<T, Error> {
+    values
+        .into_iter()
+        .try_fold(T::one(), |acc, value| acc.checked_mul(&value))
+        .ok_or(Error::Overflow)
+}
@@ -0,0 +1,88 @@
+use crate::error::Error;
+use grid_2d::{Coord, Grid, Size};
+
+const MOORE: [Coord; 8] = [
+    Coord::new(-1, -1),
+    Coord::new(0, -1),
+    Coord::new(1, -1),
+    Coord::new(-1, 0),
+    Coord::new(1, 0),
+    Coord::new(-1, 1),
+    Coord::new(0, 1),
+    Coord::new(1, 1),
+];
+
+const VON_NEUMANN: [Coord; 4] = [
+    Coord::new(0, -1),
+    Coord::new(-1, 0),
+    Coord::new(1, 0),
+    Coord::new(0, 1),
+];
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum Neighbourhood {
+    // All eight surrounding cells, diagonals included
+    #[default]
+    Moore,
+    // The four orthogonally adjacent cells
+    VonNeumann,
+    Custom(Vec<Coord>),
+}
+
+impl Neighbourhood {
+    pub fn offsets(&self) -> &[Coord] {
+        match self {
+            Neighbourhood::Moore => &MOORE,
+            Neighbourhood::VonNeumann => &VON_NEUMANN,
+            Neighbourhood::Custom(offsets) => offsets,
+        }
+    }
+}
+
+// Cells in the neighbourhood of `coord` that lie inside the grid.
+pub fn neighbours<'a, T>(
+    grid: &'a Grid<T>,
+    coord: Coord,
+    neighbourhood: &'a Neighbourhood,
+) -> impl Iterator<Item = (Coord, &'a T)> + 'a {
+    neighbourhood.offsets().iter().filter_map(move |offset| {
+        let neighbour = coord + *offset;
+        grid.get(neighbour).map(|cell| (neighbour, cell))
+    })
+}
+
+// Reads a grid with one row per line. Every row has to be as wide as the first, and every
+// character has to map to a cell.
+pub fn parse_grid<T>(input: &str, cell: impl Fn(char) -> Option<T>) -> Result<Grid<T>, Error> {
+    let width = input.lines().next().map_or(0, |line| line.chars().count());
+    let mut cells = vec![];
+    let mut height = 0;
+
+    for (row, line) in input.lines().enumerate() {
+        if line.chars().count() != width {
+            return Err(Error::Parse(format!(
+                "row {} is not {} cells wide",
+                row + 1,
+                width
+            )));
+        }
+
+        for (column, c) in line.chars().enumerate() {
+            cells.push(cell(c).ok_or_else(|| {
+                Error::Parse(format!(
+                    "unexpected {:?} at row {}, column {}",
+                    c,
+                    row + 1,
+                    column + 1
+                ))
+            })?);
+        }
+
+        height += 1;
+    }
+
+    Ok(Grid::new_iterator(
+        Size::new(width as u32, height),
+        cells.into_iter(),
+    ))
+}
@@ -1,24 +1,90 @@
-mod day01;
-mod day02;
-mod day03;
-mod day04;
-mod day05;
-mod day06;
-mod day07;
-mod day08;
-mod day09;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day17;
-mod day18;
-mod day19;
-mod day20;
-mod day21;
+pub mod day01;
+pub mod day02;
+pub mod day03;
+pub mod day04;
+pub mod day05;
+pub mod day06;
+pub mod day07;
+pub mod day08;
+pub mod day09;
+pub mod day10;
+pub mod day11;
+pub mod day12;
+pub mod day13;
+pub mod day14;
+pub mod day15;
+pub mod day16;
+pub mod day17;
+pub mod day18;
+pub mod day19;
+pub mod day20;
+pub mod day21;
+// Unfinished, so it stays out of DAYS and the public API until both parts are solved
+#[allow(dead_code)]
 mod day22;
+pub mod error;
+pub mod grid;
+pub mod solution;
 
+pub use day01::Day01;
+pub use day02::Day02;
+pub use day03::Day03;
+pub use day04::Day04;
+pub use day05::Day05;
+pub use day06::Day06;
+pub use day07::Day07;
+pub use day08::Day08;
+pub use day09::Day09;
+pub use day10::Day10;
+pub use day11::Day11;
+pub use day12::Day12;
+pub use day13::Day13;
+pub use day14::Day14;
+pub use day15::Day15;
+pub use day16::Day16;
+pub use day17::Day17;
+pub use day18::Day18;
+pub use day19::Day19;
+pub use day20::Day20;
+pub use day21::Day21;
+
+use solution::{solve, Part, PartAnswers};
+use std::ops::RangeInclusive;
+
+pub const DAYS: RangeInclusive<u32> = 1..=21;
+
+// Runs the given parts of a day on its puzzle input, or returns None for a day with no solution.
+pub fn solve_day(day: u32, input: &str, parts: &[Part]) -> Option<PartAnswers> {
+    let solve: fn(&str, &[Part]) -> PartAnswers = match day {
+        1 => solve::<Day01>,
+        2 => solve::<Day02>,
+        3 => solve::<Day03>,
+        4 => solve::<Day04>,
+        5 => solve::<Day05>,
+        6 => solve::<Day06>,
+        7 => solve::<Day07>,
+        8 => solve::<Day08>,
+        9 => solve::<Day09>,
+        10 => solve::<Day10>,
+        11 => solve::<Day11>,
+        12 => solve::<Day12>,
+        13 => solve::<Day13>,
+        14 => solve::<Day14>,
+        15 => solve::<Day15>,
+        16 => solve::<Day16>,
+        17 => solve::<Day17>,
+        18 => solve::<Day18>,
+        19 => solve::<Day19>,
+        20 => solve::<Day20>,
+        21 => solve::<Day21>,
+        _ => return None,
+    };
+
+    // cargo-aoc strips trailing newlines before handing the input over, and the parsers were
+    // written against that
+    Some(solve(input.trim_end_matches('\n'), parts))
+}
+
+// Runner wiring for cargo-aoc. The #[aoc] functions it collects stay private to each day, and
+// library users go through the Solution impls instead.
 aoc_runner_derive::aoc_lib! {year = 2023 }
@@ -1 +1,268 @@
-aoc_runner_derive::aoc_main! { lib = advent_of_code_2023 }
+use advent_of_code_2023::solution::{Answer, Part};
+use advent_of_code_2023::{solve_day, DAYS};
+use std::any::Any;
+use std::io::Read;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::{env, fs, io};
+
+static USAGE: &str = "\
+Usage: advent_of_code_2023 <COMMAND> [OPTIONS]
+
+Commands:
+  day <DAY> [PART]   Run one day, or only part 1 or 2 of it
+  all                Run every day
+  range <FROM> <TO>  Run days FROM to TO inclusive
+
+Options:
+  --input <PATH>     Read the input of a single day from PATH, or from stdin if PATH is -
+  --input-dir <DIR>  Read the input of each day from DIR/dayN.txt [default: input/2023]
+  --json             Print the answers as JSON
+  --help             Print this message
+";
+
+#[derive(Debug)]
+struct Options {
+    days: Vec<u32>,
+    parts: Vec<Part>,
+    input: Option<PathBuf>,
+    input_dir: PathBuf,
+    json: bool,
+}
+
+// The answer or error for one part of a day, or an error for the whole day when its input could
+// not be read or solving it panicked.
+struct Outcome {
+    day: u32,
+    part: Option<Part>,
+    answer: Result<Answer, String>,
+}
+
+fn parse_number(value: &str, what: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid {} '{}'", what, value))
+}
+
+fn parse_day(value: &str) -> Result<u32, String> {
+    let day = parse_number(value, "day")?;
+
+    if DAYS.contains(&day) {
+        Ok(day)
+    } else {
+        Err(format!(
+            "day {} is outside {}-{}",
+            day,
+            DAYS.start(),
+            DAYS.end()
+        ))
+    }
+}
+
+fn parse_part(value: &str) -> Result<Part, String> {
+    match value {
+        "1" => Ok(Part::One),
+        "2" => Ok(Part::Two),
+        _ => Err(format!("invalid part '{}'", value)),
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut positional = vec![];
+    let mut input = None;
+    let mut input_dir = PathBuf::from("input/2023");
+    let mut json = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input = Some(PathBuf::from(args.next().ok_or("--input needs a path")?)),
+            "--input-dir" => {
+                input_dir = PathBuf::from(args.next().ok_or("--input-dir needs a directory")?)
+            }
+            "--json" => json = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{}'", arg)),
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    let both_parts = vec![Part::One, Part::Two];
+    let (days, parts) = match positional.as_slice() {
+        ["day", day] => (vec![parse_day(day)?], both_parts),
+        ["day", day, part] => (vec![parse_day(day)?], vec![parse_part(part)?]),
+        ["all"] => (DAYS.collect(), both_parts),
+        ["range", from, to] => {
+            let (from, to) = (parse_day(from)?, parse_day(to)?);
+            if from > to {
+                return Err(format!("range {}-{} is empty", from, to));
+            }
+
+            ((from..=to).collect(), both_parts)
+        }
+        [] => return Err("no command given".to_string()),
+        _ => return Err(format!("unrecognised command '{}'", positional.join(" "))),
+    };
+
+    if input.is_some() && days.len() > 1 {
+        return Err("--input can only be used when running a single day".to_string());
+    }
+
+    Ok(Options {
+        days,
+        parts,
+        input,
+        input_dir,
+        json,
+    })
+}
+
+fn read_input(options: &Options, day: u32) -> Result<String, String> {
+    let path = match &options.input {
+        Some(path) if path.as_os_str() == "-" => {
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .map_err(|error| format!("cannot read stdin: {}", error))?;
+
+            return Ok(input);
+        }
+        Some(path) => path.clone(),
+        None => options.input_dir.join(format!("day{}.txt", day)),
+    };
+
+    fs::read_to_string(&path).map_err(|error| format!("cannot read {}: {}", path.display(), error))
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match (
+        payload.downcast_ref::<&str>(),
+        payload.downcast_ref::<String>(),
+    ) {
+        (Some(message), _) => format!("panicked: {}", message),
+        (_, Some(message)) => format!("panicked: {}", message),
+        _ => "panicked".to_string(),
+    }
+}
+
+fn run(options: &Options) -> Vec<Outcome> {
+    let mut outcomes = vec![];
+
+    for &day in &options.days {
+        let input = match read_input(options, day) {
+            Ok(input) => input,
+            Err(error) => {
+                outcomes.push(Outcome {
+                    day,
+                    part: None,
+                    answer: Err(error),
+                });
+                continue;
+            }
+        };
+
+        // One day panicking shouldn't stop the rest from running
+        let answers = match panic::catch_unwind(AssertUnwindSafe(|| {
+            solve_day(day, &input, &options.parts)
+        })) {
+            Ok(answers) => answers.expect("days are checked when parsing arguments"),
+            Err(payload) => {
+                outcomes.push(Outcome {
+                    day,
+                    part: None,
+                    answer: Err(panic_message(payload.as_ref())),
+                });
+                continue;
+            }
+        };
+
+        for (part, answer) in answers {
+            outcomes.push(Outcome {
+                day,
+                part: Some(part),
+                answer: answer.map_err(|error| error.to_string()),
+            });
+        }
+    }
+
+    outcomes
+}
+
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::new();
+
+    for c in s.chars() {
+        match c {
+            '"' => escaped += "\\\"",
+            '\\' => escaped += "\\\\",
+            '\n' => escaped += "\\n",
+            c if c.is_control() => escaped += &format!("\\u{:04x}", c as u32),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn format_human(outcome: &Outcome) -> String {
+    let label = match outcome.part {
+        Some(part) => format!("Day {} - Part {}", outcome.day, part),
+        None => format!("Day {}", outcome.day),
+    };
+
+    match &outcome.answer {
+        Ok(answer) => format!("{}: {}", label, answer),
+        Err(error) => format!("{}: error: {}", label, error),
+    }
+}
+
+fn format_json(outcome: &Outcome) -> String {
+    let mut fields = vec![format!("\"day\": {}", outcome.day)];
+
+    if let Some(part) = outcome.part {
+        fields.push(format!("\"part\": {}", part));
+    }
+    match &outcome.answer {
+        Ok(answer) => fields.push(format!("\"answer\": {}", answer)),
+        Err(error) => fields.push(format!("\"error\": \"{}\"", escape_json(error))),
+    }
+
+    format!("{{{}}}", fields.join(", "))
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprint!("error: {}\n\n{}", error, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let outcomes = run(&options);
+
+    if options.json {
+        let lines: Vec<String> = outcomes
+            .iter()
+            .map(|outcome| format!("  {}", format_json(outcome)))
+            .collect();
+        println!("[\n{}\n]", lines.join(",\n"));
+    } else {
+        for outcome in &outcomes {
+            println!("{}", format_human(outcome));
+        }
+    }
+
+    if outcomes.iter().all(|outcome| outcome.answer.is_ok()) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,80 @@
+use std::fmt;
+
+// Every day's answers are integers, and i128 holds all of them whether signed or not
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Answer(pub i128);
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+macro_rules! answer_from {
+    ($($integer:ty),*) => {
+        $(
+            impl From<$integer> for Answer {
+                fn from(value: $integer) -> Self {
+                    Answer(value as i128)
+                }
+            }
+        )*
+    };
+}
+
+answer_from!(i32, u32, i64, u64, i128, usize);
+
+pub type ParseResult<T> = Result<T, Box<dyn std::error::Error>>;
+pub type PartResult = Result<Answer, Box<dyn std::error::Error>>;
+
+// A day's puzzle, callable without going through aoc-runner.
+pub trait Solution {
+    type Input;
+
+    fn parse(input: &str) -> ParseResult<Self::Input>;
+    fn part1(input: &Self::Input) -> PartResult;
+    fn part2(input: &Self::Input) -> PartResult;
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Part {
+    One,
+    Two,
+}
+
+impl fmt::Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Part::One => write!(f, "1"),
+            Part::Two => write!(f, "2"),
+        }
+    }
+}
+
+pub type PartAnswers = Vec<(Part, PartResult)>;
+
+// Parses the input once and runs each of the requested parts on it. When the input can't be
+// parsed, every requested part reports the parse error.
+pub fn solve<S: Solution>(input: &str, parts: &[Part]) -> PartAnswers {
+    let input = match S::parse(input) {
+        Ok(input) => input,
+        Err(error) => {
+            return parts
+                .iter()
+                .map(|part| (*part, Err(error.to_string().into())))
+                .collect()
+        }
+    };
+
+    parts
+        .iter()
+        .map(|part| {
+            let answer = match part {
+                Part::One => S::part1(&input),
+                Part::Two => S::part2(&input),
+            };
+
+            (*part, answer)
+        })
+        .collect()
+}
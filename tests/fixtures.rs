@@ -0,0 +1,217 @@
+// Runs every fixture under tests/fixtures. Each day has a directory holding pairs of files:
+//
+//     dayNN/<name>.txt      the puzzle input
+//     dayNN/<name>.answers  one "partN: <answer>" line for each part the input is checked against
+//
+// A part that hasn't been solved yet is listed as "partN: unsolved", and must fail with
+// Error::Unsolved rather than returning an answer.
+//
+// The puzzle inputs themselves aren't kept with the fixtures, so dayNN/puzzle.answers is checked
+// against dayN.txt in $AOC_INPUT_DIR (input/2023 by default), and skipped when that file is
+// missing. Every day in DAYS needs example fixtures covering both parts.
+
+use advent_of_code_2023::error::Error;
+use advent_of_code_2023::solution::{Answer, Part};
+use advent_of_code_2023::{solve_day, DAYS};
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+enum Expected {
+    Answer(Answer),
+    Unsolved,
+}
+
+struct Fixture {
+    day: u32,
+    name: String,
+    input: PathBuf,
+    answers: Vec<(Part, Expected)>,
+}
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+fn input_dir() -> PathBuf {
+    match std::env::var_os("AOC_INPUT_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).join("input/2023"),
+    }
+}
+
+fn parse_answers(path: &Path) -> Vec<(Part, Expected)> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (part, answer) = line
+                .split_once(':')
+                .unwrap_or_else(|| panic!("{}: expected 'partN: answer'", path.display()));
+            let part = match part.trim() {
+                "part1" => Part::One,
+                "part2" => Part::Two,
+                other => panic!("{}: unknown part '{}'", path.display(), other),
+            };
+
+            let expected = match answer.trim() {
+                "unsolved" => Expected::Unsolved,
+                answer => Expected::Answer(Answer(answer.parse().unwrap_or_else(|_| {
+                    panic!("{}: invalid answer '{}'", path.display(), answer)
+                }))),
+            };
+
+            (part, expected)
+        })
+        .collect()
+}
+
+fn discover() -> Vec<Fixture> {
+    let mut fixtures = vec![];
+
+    let mut days: Vec<PathBuf> = fs::read_dir(fixtures_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    days.sort();
+
+    for day_dir in days {
+        let day_name = day_dir.file_name().unwrap().to_str().unwrap().to_string();
+        let day: u32 = day_name
+            .strip_prefix("day")
+            .and_then(|day| day.parse().ok())
+            .unwrap_or_else(|| panic!("unexpected fixture directory {}", day_name));
+
+        let mut answer_files: Vec<PathBuf> = fs::read_dir(&day_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "answers")
+            })
+            .collect();
+        answer_files.sort();
+
+        for answer_file in answer_files {
+            let name = answer_file
+                .file_stem()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string();
+            let input = if name == "puzzle" {
+                input_dir().join(format!("day{}.txt", day))
+            } else {
+                answer_file.with_extension("txt")
+            };
+
+            fixtures.push(Fixture {
+                day,
+                name: format!("{}/{}", day_name, name),
+                input,
+                answers: parse_answers(&answer_file),
+            });
+        }
+    }
+
+    fixtures
+}
+
+#[test]
+fn fixtures() {
+    let mut failures = vec![];
+    let mut checked = 0;
+
+    for fixture in discover() {
+        let Ok(input) = fs::read_to_string(&fixture.input) else {
+            eprintln!(
+                "skipping {}: no input at {}",
+                fixture.name,
+                fixture.input.display()
+            );
+            continue;
+        };
+
+        let parts: Vec<Part> = fixture.answers.iter().map(|(part, _)| *part).collect();
+        let results =
+            panic::catch_unwind(AssertUnwindSafe(|| solve_day(fixture.day, &input, &parts)));
+
+        let results = match results {
+            Ok(Some(results)) => results,
+            Ok(None) => {
+                failures.push(format!(
+                    "{}: no solution for day {}",
+                    fixture.name, fixture.day
+                ));
+                continue;
+            }
+            Err(_) => {
+                failures.push(format!("{}: panicked", fixture.name));
+                continue;
+            }
+        };
+
+        for ((part, expected), (_, result)) in fixture.answers.iter().zip(results) {
+            checked += 1;
+
+            match (expected, result) {
+                (Expected::Answer(expected), Ok(answer)) if answer == *expected => {}
+                (Expected::Answer(expected), Ok(answer)) => failures.push(format!(
+                    "{} part {}: expected {}, got {}",
+                    fixture.name, part, expected, answer
+                )),
+                (Expected::Answer(expected), Err(error)) => failures.push(format!(
+                    "{} part {}: expected {}, got error: {}",
+                    fixture.name, part, expected, error
+                )),
+                (Expected::Unsolved, Err(error))
+                    if error.downcast_ref::<Error>() == Some(&Error::Unsolved) => {}
+                (Expected::Unsolved, Ok(answer)) => failures.push(format!(
+                    "{} part {}: marked unsolved, but got {}",
+                    fixture.name, part, answer
+                )),
+                (Expected::Unsolved, Err(error)) => failures.push(format!(
+                    "{} part {}: marked unsolved, but got error: {}",
+                    fixture.name, part, error
+                )),
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "fixture failures:\n{}",
+        failures.join("\n")
+    );
+    assert!(checked > 0, "no fixtures found");
+}
+
+#[test]
+fn every_day_has_an_example() {
+    let fixtures = discover();
+
+    // A part counts as covered by an answer or an explicit "unsolved", so gaps can't go unnoticed
+    let mut missing = vec![];
+    for day in DAYS {
+        for part in [Part::One, Part::Two] {
+            let covered = fixtures.iter().any(|fixture| {
+                fixture.day == day
+                    && fixture.input.starts_with(fixtures_dir())
+                    && fixture
+                        .answers
+                        .iter()
+                        .any(|(answer_part, _)| *answer_part == part)
+            });
+            if !covered {
+                missing.push(format!("day {} part {}", day, part));
+            }
+        }
+    }
+
+    assert!(
+        missing.is_empty(),
+        "no example fixtures for {}",
+        missing.join(", ")
+    );
+}